@@ -0,0 +1,87 @@
+//! Batch verification of many proofs, deserializing each distinct `Vk` only once.
+
+use crate::{verify_prepared, Plonky2Config, PreparedVk, Proof, VerifyError, Vk};
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+/// Verify every `(vk, proof, pubs)` item, deserializing `Vk`s with the same bytes only once.
+///
+/// Returns a result for each item, in the same order as `items`.
+pub fn verify_batch<'a, I>(items: I) -> Vec<Result<(), VerifyError>>
+where
+    I: IntoIterator<Item = (&'a Vk, &'a Proof, &'a [u8])>,
+{
+    let mut prepared: BTreeMap<(Plonky2Config, &'a [u8]), Result<PreparedVk, VerifyError>> =
+        BTreeMap::new();
+
+    items
+        .into_iter()
+        .map(|(vk, proof, pubs)| {
+            let vk = prepared
+                .entry((vk.config, vk.bytes.as_slice()))
+                .or_insert_with(|| PreparedVk::new(vk));
+            verify_item(vk, proof, pubs)
+        })
+        .collect()
+}
+
+/// Verify every `(proof, pubs)` item against the same `vk`.
+///
+/// Returns a result for each item, in the same order as `items`.
+pub fn verify_batch_with_vk<'a, I>(vk: &Vk, items: I) -> Vec<Result<(), VerifyError>>
+where
+    I: IntoIterator<Item = (&'a Proof, &'a [u8])>,
+{
+    match PreparedVk::new(vk) {
+        Ok(vk) => items
+            .into_iter()
+            .map(|(proof, pubs)| verify_prepared(&vk, proof, pubs))
+            .collect(),
+        Err(err) => items.into_iter().map(|_| Err(err.clone())).collect(),
+    }
+}
+
+/// Verify every `(vk, proof, pubs)` item across the `rayon` thread pool.
+///
+/// Produces exactly the same results as [`verify_batch`], in the same order as `items`.
+#[cfg(feature = "parallel")]
+pub fn verify_batch_parallel<'a, I>(items: I) -> Vec<Result<(), VerifyError>>
+where
+    I: IntoIterator<Item = (&'a Vk, &'a Proof, &'a [u8])>,
+{
+    use rayon::prelude::*;
+
+    let items: Vec<_> = items.into_iter().collect();
+
+    let mut unique: BTreeMap<(Plonky2Config, &'a [u8]), &'a Vk> = BTreeMap::new();
+    for &(vk, _, _) in &items {
+        unique.entry((vk.config, vk.bytes.as_slice())).or_insert(vk);
+    }
+    let unique: Vec<_> = unique.into_iter().collect();
+    let prepared: BTreeMap<_, _> = unique
+        .into_par_iter()
+        .map(|(key, vk)| (key, PreparedVk::new(vk)))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect();
+
+    items
+        .par_iter()
+        .map(|&(vk, proof, pubs)| {
+            verify_item(&prepared[&(vk.config, vk.bytes.as_slice())], proof, pubs)
+        })
+        .collect()
+}
+
+fn verify_item(
+    vk: &Result<PreparedVk, VerifyError>,
+    proof: &Proof,
+    pubs: &[u8],
+) -> Result<(), VerifyError> {
+    vk.as_ref()
+        .map_err(Clone::clone)
+        .and_then(|vk| verify_prepared(vk, proof, pubs))
+}
@@ -2,7 +2,6 @@ mod formats;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use plonky2_verifier::Plonky2Config;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::PathBuf;
@@ -37,8 +36,8 @@ struct VkArgs {
     input: PathBuf,
     output: Option<PathBuf>,
 
-    #[arg(short, long, value_enum, default_value_t = Plonky2Config::default())]
-    config: Plonky2Config,
+    #[arg(short, long, value_enum, default_value_t = formats::ConfigArg::default())]
+    config: formats::ConfigArg,
 }
 
 #[derive(Debug, Parser)]
@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::str::FromStr;
+use std::time::Instant;
 use std::usize;
 
 use anyhow::Result;
@@ -12,7 +13,7 @@ use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
 use plonky2::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
 use plonky2::plonk::proof::ProofWithPublicInputs;
 use plonky2::util::serialization::Write;
-use plonky2_verifier::ZKVerifyGateSerializer;
+use plonky2_verifier::{verify, Plonky2Config, Proof, Pubs, Vk, ZKVerifyGateSerializer};
 
 #[derive(Copy, Clone, Default, PartialEq, Eq, Debug, ValueEnum)]
 enum HashFunction {
@@ -47,6 +48,10 @@ struct Args {
     /// Use compression.
     #[arg(short, long, default_value_t = false)]
     compress: bool,
+    /// Number of verifications timed to compare this crate against `plonky2` verifier, and
+    /// native compressed verification against decompressing first.
+    #[arg(long, default_value_t = 10)]
+    iterations: u32,
 }
 
 /// An example of using Plonky2 to prove a statement of the form
@@ -64,18 +69,33 @@ fn main() -> Result<()> {
             const D: usize = 2;
             type C = PoseidonGoldilocksConfig;
             type F = <C as GenericConfig<D>>::F;
-            main_impl::<D, C, F>(num_cycles, args.compress)
+            main_impl::<D, C, F>(
+                num_cycles,
+                Plonky2Config::Poseidon,
+                args.compress,
+                args.iterations,
+            )
         }
         HashFunction::Keccak => {
             const D: usize = 2;
             type C = KeccakGoldilocksConfig;
             type F = <C as GenericConfig<D>>::F;
-            main_impl::<D, C, F>(num_cycles, args.compress)
+            main_impl::<D, C, F>(
+                num_cycles,
+                Plonky2Config::Keccak,
+                args.compress,
+                args.iterations,
+            )
         }
     }
 }
 
-fn main_impl<const D: usize, C, F>(num_cycles: u64, compress: bool) -> Result<()>
+fn main_impl<const D: usize, C, F>(
+    num_cycles: u64,
+    config: Plonky2Config,
+    compress: bool,
+    iterations: u32,
+) -> Result<()>
 where
     C: GenericConfig<D, F = F>,
     F: RichField + Extendable<D>,
@@ -89,34 +109,58 @@ where
         .verifier_data()
         .to_bytes(&ZKVerifyGateSerializer)
         .unwrap();
+    let verifier_data = data.verifier_data();
 
     let mut proof_bytes = Vec::new();
-    let mut pubs_bytes = Vec::new();
     if compress {
         let compressed_proof = data.compress(proof.clone())?;
         proof_bytes
             .write_compressed_proof(&compressed_proof.proof)
             .unwrap();
-        pubs_bytes
-            .write_usize(compressed_proof.public_inputs.len())
-            .unwrap();
-        pubs_bytes
-            .write_field_vec(compressed_proof.public_inputs.as_slice())
-            .unwrap();
+        time("plonky2 verify_compressed", iterations, || {
+            verifier_data.verify_compressed(compressed_proof.clone())
+        })?;
+        time("plonky2 decompress and verify", iterations, || {
+            let proof = compressed_proof.clone().decompress(
+                &verifier_data.verifier_only.circuit_digest,
+                &verifier_data.common,
+            )?;
+            verifier_data.verify(proof)
+        })?;
     } else {
         proof_bytes.write_proof(&proof.proof).unwrap();
-        pubs_bytes.write_usize(proof.public_inputs.len()).unwrap();
-        pubs_bytes
-            .write_field_vec(proof.public_inputs.as_slice())
-            .unwrap();
+        time("plonky2 verify", iterations, || {
+            verifier_data.verify(proof.clone())
+        })?;
     }
+    let pubs = Pubs::from_fields(&proof.public_inputs);
+
+    let vk = Vk {
+        config,
+        bytes: vk_bytes,
+    };
+    let serialized_proof = Proof {
+        compressed: compress,
+        bytes: proof_bytes,
+    };
+    time("plonky2_verifier verify", iterations, || {
+        Ok(verify(&vk, &serialized_proof, &pubs.bytes)?)
+    })?;
+
+    save_to_bin_file(&vk.bytes, "vk.bin").unwrap();
+    save_to_bin_file(&serialized_proof.bytes, "proof.bin").unwrap();
+    save_to_bin_file(&pubs.bytes, "pubs.bin").unwrap();
 
-    data.verify(proof.clone()).unwrap();
-
-    save_to_bin_file(&vk_bytes, "vk.bin").unwrap();
-    save_to_bin_file(&proof_bytes, "proof.bin").unwrap();
-    save_to_bin_file(&pubs_bytes, "pubs.bin").unwrap();
+    Ok(())
+}
 
+/// Prints the average time `verify` takes over `iterations` runs.
+fn time(label: &str, iterations: u32, mut verify: impl FnMut() -> Result<()>) -> Result<()> {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        verify()?;
+    }
+    println!("{} = {:?}", label, start.elapsed() / iterations.max(1));
     Ok(())
 }
 
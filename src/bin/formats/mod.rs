@@ -1,9 +1,32 @@
 use anyhow::{Context, Result};
 use clap::ValueEnum;
 use plonky2_verifier::Vk;
-use plonky2_verifier::{Plonky2Config, Proof};
+use plonky2_verifier::{detect_config, Plonky2Config, Proof};
 use std::io;
 
+/// Plonky2 config of a verifier circuit data.
+#[derive(Copy, Default, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Hash)]
+pub enum ConfigArg {
+    /// Keccak over Goldilocks config.
+    Keccak,
+    /// Poseidon over Goldilocks config.
+    #[default]
+    Poseidon,
+    /// Detect the config from the verifier circuit data itself.
+    Auto,
+}
+
+impl ConfigArg {
+    /// Resolves the config for the given verifier circuit data `bytes`.
+    pub fn resolve(&self, bytes: &[u8]) -> Result<Plonky2Config> {
+        match self {
+            ConfigArg::Keccak => Ok(Plonky2Config::Keccak),
+            ConfigArg::Poseidon => Ok(Plonky2Config::Poseidon),
+            ConfigArg::Auto => detect_config(bytes).context("Failed to detect plonky2 config"),
+        }
+    }
+}
+
 /// Supported formats for input file.
 #[derive(Copy, Default, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Hash)]
 pub enum InFormat {
@@ -16,7 +39,7 @@ pub enum InFormat {
 
 impl InFormat {
     /// Decodes the verifier circuit data from the specified format.
-    pub fn decode_vk(&self, vk_bytes: Vec<u8>, config: Plonky2Config) -> Result<Vk> {
+    pub fn decode_vk(&self, vk_bytes: Vec<u8>, config: ConfigArg) -> Result<Vk> {
         let bytes = match self {
             InFormat::Bytes => vk_bytes,
             InFormat::Hex => {
@@ -30,6 +53,7 @@ impl InFormat {
             }
         };
 
+        let config = config.resolve(&bytes)?;
         Ok(Vk { bytes, config })
     }
 
@@ -0,0 +1,28 @@
+//! Canonical encoding of verification keys.
+
+use crate::ZKVerifyGateSerializer;
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::GenericConfig;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Canonical encoding of decoded `vk`, serialized with `ZKVerifyGateSerializer`.
+pub(crate) fn encode_vk<F, C, const D: usize>(vk: &VerifierCircuitData<F, C, D>) -> Option<Vec<u8>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    vk.to_bytes(&ZKVerifyGateSerializer).ok()
+}
+
+/// Offset of the first byte where `bytes` and `expected` differ, if any.
+pub(crate) fn first_difference(bytes: &[u8], expected: &[u8]) -> Option<usize> {
+    bytes
+        .iter()
+        .zip(expected)
+        .position(|(byte, expected)| byte != expected)
+        .or_else(|| (bytes.len() != expected.len()).then_some(bytes.len().min(expected.len())))
+}
@@ -0,0 +1,71 @@
+//! Fiat-Shamir transcript of compressed proofs, checked before handing them to `plonky2`.
+
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::challenger::Challenger;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::{GenericConfig, Hasher};
+use plonky2::plonk::proof::{CompressedProof, CompressedProofWithPublicInputs};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Recompute FRI query indices the verifier will derive for compressed `proof`.
+///
+/// Compressed proofs only carry query rounds for the indices they declare, and `plonky2`
+/// panics when decompressing a proof whose transcript leads to different ones. This mirrors
+/// the challenge derivation of `plonky2` verifier, so such proofs can be rejected upfront.
+///
+/// `plonky2` doesn't accept precomputed challenges, so the transcript is hashed twice for
+/// compressed proofs: here and again by `plonky2`. It takes a few dozen permutations, small
+/// next to the Merkle paths of the queries, and is accounted for by the
+/// [`OpCounters`](crate::OpCounters) and [`Cost`](crate::Cost) of compressed proofs.
+pub(crate) fn fri_query_indices<F, C, const D: usize>(
+    proof: &CompressedProofWithPublicInputs<F, C, D>,
+    circuit_digest: &<C::Hasher as Hasher<F>>::Hash,
+    common_data: &CommonCircuitData<F, D>,
+) -> Vec<usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let CompressedProof {
+        wires_cap,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
+        openings,
+        opening_proof,
+    } = &proof.proof;
+    let config = &common_data.config;
+    // Betas and gammas, followed by the additional lookup challenges if the circuit has lookups.
+    let num_permutation_challenges = if common_data.num_lookup_polys != 0 {
+        4 * config.num_challenges
+    } else {
+        2 * config.num_challenges
+    };
+
+    let mut challenger = Challenger::<F, C::Hasher>::new();
+    challenger.observe_hash::<C::Hasher>(*circuit_digest);
+    challenger.observe_hash::<C::InnerHasher>(C::InnerHasher::hash_no_pad(&proof.public_inputs));
+
+    challenger.observe_cap::<C::Hasher>(wires_cap);
+    challenger.get_n_challenges(num_permutation_challenges);
+
+    challenger.observe_cap::<C::Hasher>(plonk_zs_partial_products_cap);
+    challenger.get_n_challenges(config.num_challenges);
+
+    challenger.observe_cap::<C::Hasher>(quotient_polys_cap);
+    challenger.get_extension_challenge::<D>();
+
+    challenger.observe_openings(&openings.to_fri_openings());
+
+    challenger
+        .fri_challenges::<C, D>(
+            &opening_proof.commit_phase_merkle_caps,
+            &opening_proof.final_poly,
+            opening_proof.pow_witness,
+            common_data.degree_bits(),
+            &config.fri_config,
+        )
+        .fri_query_indices
+}
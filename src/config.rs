@@ -4,9 +4,17 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "converter")]
 use clap::ValueEnum;
 
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
 /// Config for `Plonky2` proving system with options, acceptable by `zkVerify`.
-#[derive(Copy, Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(
+    Copy, Default, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 #[cfg_attr(feature = "converter", derive(ValueEnum))]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
 pub enum Plonky2Config {
     /// Preset Keccak over Goldilocks config available in `plonky2`
     Keccak,
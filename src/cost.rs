@@ -0,0 +1,72 @@
+//! Deterministic estimate of the cost of verifying a proof against a verification key.
+//!
+//! The estimate only depends on the decoded `CommonCircuitData`, the hasher of the
+//! `Plonky2Config` and the proof compression flag, so a runtime can charge for
+//! verification before running it.
+
+use crate::instrument::{self, OpCounters, Stage};
+use crate::{Plonky2Config, PreparedCircuit, PreparedVk};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+
+/// Estimated cost of a verification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cost {
+    /// Hash permutations and compressions, of Poseidon or Keccak-f depending on the config.
+    pub permutations: u64,
+    /// Field and extension field arithmetic operations.
+    pub field_ops: u64,
+}
+
+impl Cost {
+    /// Total weight of this cost, given the weight of a single permutation and field operation.
+    pub fn weight(&self, permutation: u64, field_op: u64) -> u64 {
+        self.permutations
+            .saturating_mul(permutation)
+            .saturating_add(self.field_ops.saturating_mul(field_op))
+    }
+}
+
+impl From<OpCounters> for Cost {
+    fn from(counters: OpCounters) -> Self {
+        Self {
+            permutations: counters
+                .hash_permutations
+                .saturating_add(counters.hash_compressions),
+            field_ops: counters.field_muls.saturating_add(counters.extension_muls),
+        }
+    }
+}
+
+/// Estimate the cost of verifying a proof against `vk`.
+pub fn estimate(vk: &PreparedVk, compressed: bool) -> Cost {
+    match vk.circuit() {
+        PreparedCircuit::Keccak(vk) => {
+            estimate_circuit(&vk.common, Plonky2Config::Keccak, compressed)
+        }
+        PreparedCircuit::Poseidon(vk) => {
+            estimate_circuit(&vk.common, Plonky2Config::Poseidon, compressed)
+        }
+    }
+}
+
+/// Estimate the cost of verifying a proof of the circuit described by `common`,
+/// hashed with the hasher of `config`.
+///
+/// This is the cost of the operations counted by
+/// [`verify_instrumented`](crate::verify_instrumented) for a valid proof.
+pub fn estimate_circuit<F, const D: usize>(
+    common: &CommonCircuitData<F, D>,
+    config: Plonky2Config,
+    compressed: bool,
+) -> Cost
+where
+    F: RichField + Extendable<D>,
+{
+    let mut counters = instrument::model(common, config, Stage::Verification { compressed });
+    if compressed {
+        counters += instrument::model(common, config, Stage::Replay);
+    }
+    counters.into()
+}
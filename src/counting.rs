@@ -0,0 +1,245 @@
+//! `plonky2` config counting the hashing done by the verifier.
+//!
+//! [`Counting`] wraps a `GenericConfig` so its hashers and transcript permutation tally
+//! every call before delegating to the wrapped ones. `plonky2` hashers have no state, so
+//! the tally is kept per thread with `std`, and in a global shared by every thread
+//! without it.
+
+use crate::{instrument, OpCounters, Plonky2Config};
+use core::marker::PhantomData;
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::hash::hashing::PlonkyPermutation;
+use plonky2::hash::keccak::KeccakHash;
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::BoolTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{VerifierCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use serde::Serialize;
+
+/// Hasher of a [`Plonky2Config`], so the permutations of its hashes can be counted.
+pub(crate) trait ConfigHasher {
+    /// Config hashing with this hasher.
+    const CONFIG: Plonky2Config;
+}
+
+impl ConfigHasher for PoseidonHash {
+    const CONFIG: Plonky2Config = Plonky2Config::Poseidon;
+}
+
+impl<const N: usize> ConfigHasher for KeccakHash<N> {
+    const CONFIG: Plonky2Config = Plonky2Config::Keccak;
+}
+
+/// Config `C` whose hashers count the operations they perform.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(bound = "")]
+pub(crate) struct Counting<C>(PhantomData<C>);
+
+impl<C, const D: usize> GenericConfig<D> for Counting<C>
+where
+    C: GenericConfig<D>,
+    C::Hasher: ConfigHasher,
+    C::InnerHasher: ConfigHasher,
+{
+    type F = C::F;
+    type FE = C::FE;
+    type Hasher = Counted<C::Hasher>;
+    type InnerHasher = Counted<C::InnerHasher>;
+}
+
+/// Hasher `H` counting its hashes, compressions and Merkle leaves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Counted<H>(PhantomData<H>);
+
+impl<F, H> Hasher<F> for Counted<H>
+where
+    F: RichField,
+    H: Hasher<F> + ConfigHasher,
+{
+    const HASH_SIZE: usize = H::HASH_SIZE;
+    type Hash = H::Hash;
+    type Permutation = CountedPermutation<H::Permutation>;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        tally::record(
+            Op::Permutations,
+            instrument::permutations(H::CONFIG, input.len() as u64),
+        );
+        H::hash_no_pad(input)
+    }
+
+    /// Only used on the leaves of Merkle paths, each counted as a path.
+    fn hash_or_noop(inputs: &[F]) -> Self::Hash {
+        tally::record(Op::MerklePaths, 1);
+        // Leaves no larger than a digest are used as is, without hashing.
+        if inputs.len() * size_of::<u64>() <= Self::HASH_SIZE {
+            H::hash_or_noop(inputs)
+        } else {
+            Self::hash_no_pad(inputs)
+        }
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        tally::record(Op::Compressions, 1);
+        H::two_to_one(left, right)
+    }
+}
+
+impl<F, H> AlgebraicHasher<F> for Counted<H>
+where
+    F: RichField,
+    H: AlgebraicHasher<F> + ConfigHasher,
+{
+    type AlgebraicPermutation = H::AlgebraicPermutation;
+
+    fn permute_swapped<const D: usize>(
+        inputs: Self::AlgebraicPermutation,
+        swap: BoolTarget,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Self::AlgebraicPermutation
+    where
+        F: RichField + Extendable<D>,
+    {
+        H::permute_swapped(inputs, swap, builder)
+    }
+}
+
+/// Permutation `P` of a transcript sponge, counting its permutations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CountedPermutation<P>(P);
+
+impl<T, P: AsRef<[T]>> AsRef<[T]> for CountedPermutation<P> {
+    fn as_ref(&self) -> &[T] {
+        self.0.as_ref()
+    }
+}
+
+impl<T, P> PlonkyPermutation<T> for CountedPermutation<P>
+where
+    T: Copy + Default,
+    P: PlonkyPermutation<T>,
+{
+    const RATE: usize = P::RATE;
+    const WIDTH: usize = P::WIDTH;
+
+    fn new<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(P::new(iter))
+    }
+
+    fn set_elt(&mut self, elt: T, idx: usize) {
+        self.0.set_elt(elt, idx);
+    }
+
+    fn set_from_slice(&mut self, elts: &[T], start_idx: usize) {
+        self.0.set_from_slice(elts, start_idx);
+    }
+
+    fn set_from_iter<I: IntoIterator<Item = T>>(&mut self, elts: I, start_idx: usize) {
+        self.0.set_from_iter(elts, start_idx);
+    }
+
+    fn permute(&mut self) {
+        tally::record(Op::Permutations, 1);
+        self.0.permute();
+    }
+
+    fn squeeze(&self) -> &[T] {
+        self.0.squeeze()
+    }
+}
+
+/// `vk` with hashers counting their operations.
+pub(crate) fn counting<F, C, const D: usize>(
+    vk: &VerifierCircuitData<F, C, D>,
+) -> VerifierCircuitData<F, Counting<C>, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: ConfigHasher,
+    C::InnerHasher: ConfigHasher,
+{
+    VerifierCircuitData {
+        verifier_only: VerifierOnlyCircuitData {
+            constants_sigmas_cap: MerkleCap(vk.verifier_only.constants_sigmas_cap.0.clone()),
+            circuit_digest: vk.verifier_only.circuit_digest,
+        },
+        common: vk.common.clone(),
+    }
+}
+
+/// Run `f`, returning its result and the hashing operations of the counting hashers it did.
+///
+/// Only the hashing counters are set, the others are left to zero.
+pub(crate) fn count<T>(f: impl FnOnce() -> T) -> (T, OpCounters) {
+    tally::take();
+    let result = f();
+    (result, tally::take())
+}
+
+/// Operation tallied by the counting hashers.
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Permutations,
+    Compressions,
+    MerklePaths,
+}
+
+#[cfg(feature = "std")]
+mod tally {
+    use super::Op;
+    use crate::OpCounters;
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static TALLY: Cell<OpCounters> = Cell::new(OpCounters::default());
+    }
+
+    /// Add `count` operations `op` to the tally of this thread.
+    pub(super) fn record(op: Op, count: u64) {
+        TALLY.with(|tally| {
+            let mut counters = tally.get();
+            let counter = match op {
+                Op::Permutations => &mut counters.hash_permutations,
+                Op::Compressions => &mut counters.hash_compressions,
+                Op::MerklePaths => &mut counters.merkle_paths,
+            };
+            *counter = counter.saturating_add(count);
+            tally.set(counters);
+        });
+    }
+
+    /// Tally of this thread, reset to zero.
+    pub(super) fn take() -> OpCounters {
+        TALLY.with(Cell::take)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod tally {
+    use super::Op;
+    use crate::OpCounters;
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    static TALLY: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+    /// Add `count` operations `op` to the tally.
+    pub(super) fn record(op: Op, count: u64) {
+        TALLY[op as usize].fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Tally, reset to zero.
+    pub(super) fn take() -> OpCounters {
+        let [permutations, compressions, merkle_paths] = TALLY
+            .each_ref()
+            .map(|counter| counter.swap(0, Ordering::Relaxed));
+        OpCounters {
+            hash_permutations: permutations,
+            hash_compressions: compressions,
+            merkle_paths,
+            ..OpCounters::default()
+        }
+    }
+}
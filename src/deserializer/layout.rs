@@ -0,0 +1,241 @@
+//! Layout of serialized verification keys, scanned before handing them to `plonky2`.
+//!
+//! `plonky2` readers trust the lengths and counts they read: they allocate for them upfront
+//! and may panic on values the remaining bytes can't hold. Scanning the layout first bounds
+//! every such value by the bytes left, so the readers only ever see plausible ones.
+
+use super::{Component, Failure};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::config::{GenericConfig, Hasher};
+
+/// Size of a serialized `usize`.
+const USIZE: usize = size_of::<u64>();
+/// Size of the circuit config fields before the FRI config: 6 `usize`s and 2 `bool`s.
+const CONFIG_PREFIX: usize = 6 * USIZE + 2;
+/// Size of the proof-of-work bits of the FRI config.
+const POW_BITS: usize = size_of::<u32>();
+/// Tag of `FriReductionStrategy::Fixed`, the only strategy with a variable size.
+const FIXED_STRATEGY: u8 = 0;
+/// Tag of `FriReductionStrategy::ConstantArityBits`.
+const CONSTANT_ARITY_BITS_STRATEGY: u8 = 1;
+/// Tag of `FriReductionStrategy::MinSize`.
+const MIN_SIZE_STRATEGY: u8 = 2;
+/// Largest Merkle cap height whose cap size fits a `usize`.
+const MAX_CAP_HEIGHT: u64 = usize::BITS as u64 - 1;
+
+/// Offsets and parameters of a serialized verification key.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct VkLayout {
+    /// End of `VerifierOnlyCircuitData`.
+    pub(crate) verifier_only_end: usize,
+    /// Start of the coset shifts `k_is`.
+    pub(crate) k_is: usize,
+    /// Number of coset shifts.
+    pub(crate) num_k_is: usize,
+    /// Start of the first gate.
+    pub(crate) gates: usize,
+    /// Height of the constants and sigmas cap.
+    pub(crate) constants_sigmas_cap_height: u64,
+    /// Merkle cap height of the FRI config.
+    pub(crate) cap_height: u64,
+    /// Number of FRI query rounds.
+    pub(crate) num_query_rounds: u64,
+    /// Degree bits of the circuit.
+    pub(crate) degree_bits: u64,
+    /// Number of public inputs of the circuit.
+    pub(crate) num_public_inputs: u64,
+}
+
+/// Parameters of a serialized `FriConfig`.
+struct FriConfigLayout {
+    /// Bytes of the whole config.
+    bytes: core::ops::Range<usize>,
+    rate_bits: u64,
+    cap_height: u64,
+    num_query_rounds: u64,
+}
+
+/// Cursor over serialized bytes, failing at the offset of the first implausible value.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    component: Component,
+}
+
+impl Cursor<'_> {
+    fn fail_at(&self, offset: usize) -> Failure {
+        Failure {
+            component: self.component,
+            offset,
+        }
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), Failure> {
+        self.pos = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| self.fail_at(self.pos))?;
+        Ok(())
+    }
+
+    fn peek_u8(&self, at: usize) -> Result<u8, Failure> {
+        self.bytes
+            .get(self.pos + at)
+            .copied()
+            .ok_or_else(|| self.fail_at(self.pos))
+    }
+
+    fn u64(&mut self) -> Result<u64, Failure> {
+        let word = self
+            .bytes
+            .get(self.pos..)
+            .and_then(|bytes| bytes.first_chunk::<USIZE>())
+            .ok_or_else(|| self.fail_at(self.pos))?;
+        self.pos += USIZE;
+        Ok(u64::from_le_bytes(*word))
+    }
+
+    /// Read a length prefix of items of `item_size` bytes each, which must fit the bytes left.
+    fn len(&mut self, item_size: usize) -> Result<usize, Failure> {
+        let at = self.pos;
+        let len = self.u64()?;
+        usize::try_from(len)
+            .ok()
+            .filter(|&len| {
+                len.checked_mul(item_size)
+                    .is_some_and(|size| size <= self.bytes.len() - self.pos)
+            })
+            .ok_or_else(|| self.fail_at(at))
+    }
+
+    /// Skip a length-prefixed vector of items of `item_size` bytes each.
+    fn vec(&mut self, item_size: usize) -> Result<usize, Failure> {
+        let len = self.len(item_size)?;
+        self.skip(len * item_size)?;
+        Ok(len)
+    }
+
+    /// Skip a `FriConfig`, keeping the parameters the rest of the layout depends on.
+    fn fri_config(&mut self) -> Result<FriConfigLayout, Failure> {
+        let start = self.pos;
+        // Rate bits, cap height, query rounds and proof-of-work bits.
+        let rate_bits = self.u64()?;
+        let cap_height = self.u64()?;
+        let num_query_rounds = self.u64()?;
+        self.skip(POW_BITS)?;
+        let at = self.pos;
+        match self.peek_u8(0)? {
+            FIXED_STRATEGY => {
+                self.skip(1)?;
+                self.vec(USIZE)?;
+            }
+            CONSTANT_ARITY_BITS_STRATEGY => self.skip(1 + 2 * USIZE)?,
+            MIN_SIZE_STRATEGY => match self.peek_u8(1)? {
+                0 => self.skip(2)?,
+                _ => self.skip(2 + USIZE)?,
+            },
+            _ => return Err(self.fail_at(at)),
+        }
+        Ok(FriConfigLayout {
+            bytes: start..self.pos,
+            rate_bits,
+            cap_height,
+            num_query_rounds,
+        })
+    }
+}
+
+/// Scan the layout of serialized `vk`, up to its first gate.
+///
+/// Every length and count is checked against the bytes left. Gates are left to
+/// `plonky2` readers, but their number must fit the bytes left as well.
+///
+/// The FRI parameters must also be usable without overflowing: the low-degree extension
+/// must fit the two-adic subgroup of the field, reductions can't fold more than the degree
+/// and every committed tree must be at least as tall as its cap.
+pub(crate) fn scan_vk<F, C, const D: usize>(vk: &[u8]) -> Result<VkLayout, Failure>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let hash_size = <C::Hasher as Hasher<F>>::HASH_SIZE;
+    let mut cursor = Cursor {
+        bytes: vk,
+        pos: 0,
+        component: Component::VerifierOnlyData,
+    };
+
+    // Constants and sigmas cap, followed by the circuit digest.
+    let constants_sigmas_cap_height = cursor.u64()?;
+    if constants_sigmas_cap_height > MAX_CAP_HEIGHT {
+        return Err(cursor.fail_at(0));
+    }
+    let cap_len = 1usize << constants_sigmas_cap_height;
+    let size = cap_len
+        .checked_mul(hash_size)
+        .ok_or_else(|| cursor.fail_at(0))?;
+    cursor.skip(size)?;
+    cursor.skip(hash_size)?;
+    let verifier_only_end = cursor.pos;
+
+    cursor.component = Component::CommonData;
+    // Circuit config, then FRI params repeating its FRI config.
+    cursor.skip(CONFIG_PREFIX)?;
+    let fri_config = cursor.fri_config()?;
+    let params = cursor.fri_config()?;
+    if vk[fri_config.bytes.clone()] != vk[params.bytes.clone()] {
+        return Err(cursor.fail_at(params.bytes.start));
+    }
+    if constants_sigmas_cap_height != fri_config.cap_height {
+        return Err(cursor.fail_at(fri_config.bytes.start + USIZE));
+    }
+    // Reduction arity bits, degree bits and hiding flag.
+    let arity_bits_at = cursor.pos;
+    let num_reductions = cursor.vec(USIZE)?;
+    let arity_bits = vk[arity_bits_at + USIZE..][..num_reductions * USIZE]
+        .chunks_exact(USIZE)
+        .map(|word| u64::from_le_bytes(word.try_into().unwrap_or_default()))
+        .fold(0u64, u64::saturating_add);
+    let degree_bits_at = cursor.pos;
+    let degree_bits = cursor.u64()?;
+    let lde_bits = degree_bits
+        .checked_add(fri_config.rate_bits)
+        .filter(|&lde_bits| lde_bits <= F::TWO_ADICITY as u64)
+        .ok_or_else(|| cursor.fail_at(degree_bits_at))?;
+    if arity_bits > degree_bits || fri_config.cap_height > lde_bits - arity_bits {
+        return Err(cursor.fail_at(arity_bits_at));
+    }
+    cursor.skip(1)?;
+    // Selector indices and groups, each group a range of two `usize`s.
+    cursor.vec(USIZE)?;
+    cursor.vec(2 * USIZE)?;
+    // Quotient degree factor, gate constraints, constants and public inputs.
+    cursor.skip(3 * USIZE)?;
+    let num_public_inputs = cursor.u64()?;
+    let num_k_is = cursor.len(USIZE)?;
+    let k_is = cursor.pos;
+    cursor.skip(num_k_is * USIZE)?;
+    // Partial products, lookup polynomials and lookup selectors.
+    cursor.skip(3 * USIZE)?;
+    // Lookup tables, each a length-prefixed vector of `u16` pairs.
+    let num_luts = cursor.len(USIZE)?;
+    for _ in 0..num_luts {
+        cursor.vec(2 * size_of::<u16>())?;
+    }
+    // Gates, each at least a `u32` tag.
+    cursor.len(size_of::<u32>())?;
+
+    Ok(VkLayout {
+        verifier_only_end,
+        k_is,
+        num_k_is,
+        gates: cursor.pos,
+        constants_sigmas_cap_height,
+        cap_height: fri_config.cap_height,
+        num_query_rounds: fri_config.num_query_rounds,
+        degree_bits,
+        num_public_inputs,
+    })
+}
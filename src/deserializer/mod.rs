@@ -1,29 +1,318 @@
 pub mod custom;
+mod layout;
 
+use crate::{canonical, guard};
 use custom::ZKVerifyGateSerializer;
+pub(crate) use layout::VkLayout;
 
+use core::cell::Cell;
+use core::fmt;
 use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::gates::gate::GateRef;
 use plonky2::hash::hash_types::RichField;
-use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierCircuitData};
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::plonk::circuit_data::{
+    CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
 use plonky2::plonk::config::GenericConfig;
-use plonky2::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+use plonky2::plonk::proof::{
+    CompressedProof, CompressedProofWithPublicInputs, Proof, ProofWithPublicInputs,
+};
+use plonky2::util::serialization::{Buffer, GateSerializer, IoResult, Read, Write};
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
 
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 use snafu::Snafu;
 
+/// Component of a serialized verification key, proof or public inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
+pub enum Component {
+    /// `CommonCircuitData` of a verification key.
+    CommonData,
+    /// `VerifierOnlyCircuitData` of a verification key.
+    VerifierOnlyData,
+    /// Tag or parameters of a gate in `CommonCircuitData`.
+    GateTag,
+    /// Merkle caps of a proof.
+    ProofBody,
+    /// Opening set of a proof.
+    OpeningSet,
+    /// FRI proof of a proof.
+    FriProof,
+    /// Public inputs.
+    PublicInputs,
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Component::CommonData => "common circuit data",
+            Component::VerifierOnlyData => "verifier-only circuit data",
+            Component::GateTag => "gate",
+            Component::ProofBody => "proof body",
+            Component::OpeningSet => "opening set",
+            Component::FriProof => "FRI proof",
+            Component::PublicInputs => "public inputs",
+        };
+        f.write_str(name)
+    }
+}
+
 /// Deserialization error.
-#[derive(Debug, Snafu)]
+#[derive(Debug, Clone, PartialEq, Eq, Snafu)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
 pub enum DeserializeError {
-    /// Invalid proof or public inputs.
-    #[snafu(display("Invalid proof or public inputs for deserialization"))]
-    InvalidProof,
+    /// Invalid proof.
+    #[snafu(display(
+        "Invalid proof for deserialization: {} at byte {} of {}",
+        component,
+        offset,
+        len
+    ))]
+    InvalidProof {
+        /// Component that failed to deserialize.
+        component: Component,
+        /// Approximate byte offset of the failure.
+        offset: u64,
+        /// Length of the serialized proof in bytes.
+        len: u64,
+    },
     /// Invalid verification key.
-    #[snafu(display("Invalid verification key for deserialization"))]
-    InvalidVerificationKey,
+    #[snafu(display(
+        "Invalid verification key for deserialization: {} at byte {} of {}",
+        component,
+        offset,
+        len
+    ))]
+    InvalidVerificationKey {
+        /// Component that failed to deserialize.
+        component: Component,
+        /// Approximate byte offset of the failure.
+        offset: u64,
+        /// Length of the serialized verification key in bytes.
+        len: u64,
+    },
+    /// Proof has bytes left after its last component.
+    #[snafu(display("Trailing bytes in proof: consumed {} of {}", consumed, len))]
+    TrailingProofBytes {
+        /// Number of bytes consumed by the proof.
+        consumed: u64,
+        /// Length of the serialized proof in bytes.
+        len: u64,
+    },
+    /// Verification key has bytes left after its last component.
+    #[snafu(display("Trailing bytes in verification key: consumed {} of {}", consumed, len))]
+    TrailingVerificationKeyBytes {
+        /// Number of bytes consumed by the verification key.
+        consumed: u64,
+        /// Length of the serialized verification key in bytes.
+        len: u64,
+    },
+    /// Proof isn't canonically encoded, e.g. it has a non-canonical field element.
+    #[snafu(display("Non-canonical proof encoding: {} at byte {}", component, offset))]
+    NonCanonicalProof {
+        /// Component with the non-canonical encoding.
+        component: Component,
+        /// Approximate byte offset of the non-canonical encoding.
+        offset: u64,
+    },
+    /// Verification key isn't canonically encoded, e.g. it has a non-canonical field element.
+    #[snafu(display(
+        "Non-canonical verification key encoding: {} at byte {}",
+        component,
+        offset
+    ))]
+    NonCanonicalVerificationKey {
+        /// Component with the non-canonical encoding.
+        component: Component,
+        /// Approximate byte offset of the non-canonical encoding.
+        offset: u64,
+    },
+    /// Public input is not a field element below the modulus.
+    #[snafu(display("Non-canonical public input at index {}", index))]
+    NonCanonicalPublicInput {
+        /// Index of the public input.
+        index: u64,
+    },
+    /// Public inputs are not a length prefix followed by whole field elements.
+    #[snafu(display("Malformed public inputs encoding of {} bytes", len))]
+    MalformedPublicInputs {
+        /// Length of the encoding in bytes.
+        len: u64,
+    },
+    /// Length prefix of the public inputs doesn't match the number of encoded elements.
+    #[snafu(display(
+        "Public inputs length prefix mismatch: declared {} elements, encoded {}",
+        declared,
+        encoded
+    ))]
+    PublicInputsLengthPrefixMismatch {
+        /// Number of elements declared by the length prefix.
+        declared: u64,
+        /// Number of elements following the length prefix.
+        encoded: u64,
+    },
+    /// Number of public inputs doesn't match the one of the circuit.
+    #[snafu(display(
+        "Public inputs length mismatch: expected {} elements, got {}",
+        expected,
+        actual
+    ))]
+    PublicInputsLengthMismatch {
+        /// Number of public inputs of the circuit.
+        expected: u64,
+        /// Number of encoded elements.
+        actual: u64,
+    },
+}
+
+impl DeserializeError {
+    /// Component that failed to deserialize.
+    pub fn component(&self) -> Component {
+        match self {
+            DeserializeError::InvalidProof { component, .. }
+            | DeserializeError::InvalidVerificationKey { component, .. }
+            | DeserializeError::NonCanonicalProof { component, .. }
+            | DeserializeError::NonCanonicalVerificationKey { component, .. } => *component,
+            DeserializeError::TrailingProofBytes { .. } => Component::FriProof,
+            DeserializeError::TrailingVerificationKeyBytes { .. } => Component::CommonData,
+            DeserializeError::MalformedPublicInputs { .. }
+            | DeserializeError::PublicInputsLengthPrefixMismatch { .. }
+            | DeserializeError::PublicInputsLengthMismatch { .. }
+            | DeserializeError::NonCanonicalPublicInput { .. } => Component::PublicInputs,
+        }
+    }
+}
+
+/// Location in serialized data where deserialization failed.
+struct Failure {
+    component: Component,
+    offset: usize,
+}
+
+impl Failure {
+    fn proof(self, proof: &[u8]) -> DeserializeError {
+        DeserializeError::InvalidProof {
+            component: self.component,
+            offset: self.offset as u64,
+            len: proof.len() as u64,
+        }
+    }
+
+    fn vk(self, vk: &[u8]) -> DeserializeError {
+        DeserializeError::InvalidVerificationKey {
+            component: self.component,
+            offset: self.offset as u64,
+            len: vk.len() as u64,
+        }
+    }
+
+    fn non_canonical_proof(self) -> DeserializeError {
+        DeserializeError::NonCanonicalProof {
+            component: self.component,
+            offset: self.offset as u64,
+        }
+    }
+
+    fn non_canonical_vk(self) -> DeserializeError {
+        DeserializeError::NonCanonicalVerificationKey {
+            component: self.component,
+            offset: self.offset as u64,
+        }
+    }
+}
+
+/// Read a single `component` from `buffer`, remembering where it failed.
+///
+/// A panic of `plonky2` reader on malformed input is reported as a failure as well.
+fn read_component<'a, T>(
+    buffer: &mut Buffer<'a>,
+    component: Component,
+    read: impl FnOnce(&mut Buffer<'a>) -> IoResult<T>,
+) -> Result<T, Failure> {
+    guard::catch(|| read(&mut *buffer))
+        .and_then(Result::ok)
+        .ok_or_else(|| Failure {
+            component,
+            offset: buffer.pos(),
+        })
+}
+
+/// Ensure `bytes` are exactly the canonical encoding written by `encode`.
+///
+/// `ends` are the end offsets of the components in `bytes`, used to name the
+/// component of the first differing byte.
+fn ensure_canonical(
+    bytes: &[u8],
+    ends: &[(Component, usize)],
+    encode: impl FnOnce(&mut Vec<u8>) -> IoResult<()>,
+) -> Result<(), Failure> {
+    let mut encoded = Vec::with_capacity(bytes.len());
+    let offset = match encode(&mut encoded) {
+        Ok(()) => canonical::first_difference(bytes, &encoded),
+        Err(_) => Some(0),
+    };
+    match offset {
+        Some(offset) => Err(Failure {
+            component: ends
+                .iter()
+                .find(|&&(_, end)| offset < end)
+                .or(ends.last())
+                .map_or(Component::ProofBody, |&(component, _)| component),
+            offset,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Gate serializer remembering where the last gate failed to deserialize.
+struct TrackingGateSerializer {
+    failed_at: Cell<Option<usize>>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D> for TrackingGateSerializer {
+    fn read_gate(
+        &self,
+        buf: &mut Buffer,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<GateRef<F, D>> {
+        let offset = buf.pos();
+        GateSerializer::<F, D>::read_gate(&ZKVerifyGateSerializer, buf, common_data)
+            .inspect_err(|_| self.failed_at.set(Some(offset)))
+    }
+
+    fn write_gate(
+        &self,
+        buf: &mut Vec<u8>,
+        gate: &GateRef<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        GateSerializer::<F, D>::write_gate(&ZKVerifyGateSerializer, buf, gate, common_data)
+    }
+}
+
+/// Scan the layout of serialized `vk` without decoding it, see [`layout::scan_vk`].
+pub(crate) fn scan_vk<F, C, const D: usize>(vk: &[u8]) -> Result<VkLayout, DeserializeError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    layout::scan_vk::<F, C, D>(vk).map_err(|failure| failure.vk(vk))
 }
 
 /// Deserialize a `Vk` from bytes to `VerifierCircuitData`.
+///
+/// Lengths and counts are checked against the bytes left before `plonky2` reads them, see
+/// [`layout::scan_vk`], so malformed bytes are rejected without relying on unwinding.
+/// The bytes must also be exactly the re-encoding of the decoded data with
+/// `ZKVerifyGateSerializer`, so every circuit has a single encoding.
 pub fn deserialize_vk<F, C, const D: usize>(
     vk: &[u8],
 ) -> Result<VerifierCircuitData<F, C, D>, DeserializeError>
@@ -31,11 +320,95 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    VerifierCircuitData::<F, C, D>::from_bytes(Vec::from(vk), &ZKVerifyGateSerializer)
-        .map_err(|_| DeserializeError::InvalidVerificationKey)
+    decode_vk::<F, C, D>(vk, &scan_vk::<F, C, D>(vk)?)
 }
 
-/// Combine a `Proof` and `Pubs` and deserialize into `ProofWithPublicInputs`.
+/// Deserialize a `Vk` like [`deserialize_vk`], given the `layout` scanned from the same
+/// bytes with [`scan_vk`], so callers can check it before anything is decoded.
+pub(crate) fn decode_vk<F, C, const D: usize>(
+    vk: &[u8],
+    layout: &VkLayout,
+) -> Result<VerifierCircuitData<F, C, D>, DeserializeError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let decoded = read_vk::<F, C, D>(vk, layout)?;
+    ensure_canonical(
+        vk,
+        &[
+            (Component::VerifierOnlyData, layout.verifier_only_end),
+            (Component::CommonData, layout.gates),
+            (Component::GateTag, vk.len()),
+        ],
+        |bytes| {
+            bytes.write_verifier_only_circuit_data(&decoded.verifier_only)?;
+            bytes.write_common_circuit_data(&decoded.common, &ZKVerifyGateSerializer)
+        },
+    )
+    .map_err(Failure::non_canonical_vk)?;
+    Ok(decoded)
+}
+
+/// Deserialize a `Vk` from bytes to `VerifierCircuitData` like [`deserialize_vk`], but
+/// accepting any encoding `plonky2` readers accept, e.g. with non-canonical field elements.
+///
+/// Coset shifts must still be canonical, as reading them may panic otherwise.
+/// `layout` must be the one scanned from `vk` with [`scan_vk`].
+pub(crate) fn read_vk<F, C, const D: usize>(
+    vk: &[u8],
+    layout: &VkLayout,
+) -> Result<VerifierCircuitData<F, C, D>, DeserializeError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    if let Some(index) = vk[layout.k_is..][..layout.num_k_is * size_of::<u64>()]
+        .chunks_exact(size_of::<u64>())
+        .position(|word| u64::from_le_bytes(word.try_into().unwrap_or_default()) >= F::ORDER)
+    {
+        return Err(DeserializeError::NonCanonicalVerificationKey {
+            component: Component::CommonData,
+            offset: (layout.k_is + index * size_of::<u64>()) as u64,
+        });
+    }
+
+    let mut buffer = Buffer::new(vk);
+    let verifier_only: VerifierOnlyCircuitData<C, D> =
+        read_component(&mut buffer, Component::VerifierOnlyData, |buffer| {
+            buffer.read_verifier_only_circuit_data()
+        })
+        .map_err(|failure| failure.vk(vk))?;
+
+    let gate_serializer = TrackingGateSerializer {
+        failed_at: Cell::new(None),
+    };
+    let common: CommonCircuitData<F, D> =
+        read_component(&mut buffer, Component::CommonData, |buffer| {
+            buffer.read_common_circuit_data(&gate_serializer)
+        })
+        .map_err(|failure| match gate_serializer.failed_at.get() {
+            Some(offset) => Failure {
+                component: Component::GateTag,
+                offset,
+            },
+            None => failure,
+        })
+        .map_err(|failure| failure.vk(vk))?;
+    if buffer.pos() != vk.len() {
+        return Err(DeserializeError::TrailingVerificationKeyBytes {
+            consumed: buffer.pos() as u64,
+            len: vk.len() as u64,
+        });
+    }
+
+    Ok(VerifierCircuitData {
+        verifier_only,
+        common,
+    })
+}
+
+/// Deserialize a `Proof` and `Pubs` into `ProofWithPublicInputs`.
 pub fn deserialize_proof_with_pubs<F, C, const D: usize>(
     proof: &[u8],
     pubs: &[u8],
@@ -45,15 +418,47 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    let mut bytes = Vec::with_capacity(proof.len() + pubs.len());
-    bytes.extend_from_slice(proof);
-    bytes.extend_from_slice(pubs);
+    let public_inputs = deserialize_circuit_pubs(pubs, common_data)?;
+    let mut buffer = Buffer::new(proof);
+    let [wires_cap, plonk_zs_partial_products_cap, quotient_polys_cap] =
+        read_caps::<F, C, D>(&mut buffer, common_data).map_err(|failure| failure.proof(proof))?;
+    let caps_end = buffer.pos();
+    let openings = read_component(&mut buffer, Component::OpeningSet, |buffer| {
+        buffer.read_opening_set::<F, C, D>(common_data)
+    })
+    .map_err(|failure| failure.proof(proof))?;
+    let openings_end = buffer.pos();
+    let opening_proof = read_component(&mut buffer, Component::FriProof, |buffer| {
+        buffer.read_fri_proof::<F, C, D>(common_data)
+    })
+    .map_err(|failure| failure.proof(proof))?;
+    ensure_consumed(&buffer, proof)?;
+
+    let decoded = Proof {
+        wires_cap,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
+        openings,
+        opening_proof,
+    };
+    ensure_canonical(
+        proof,
+        &[
+            (Component::ProofBody, caps_end),
+            (Component::OpeningSet, openings_end),
+            (Component::FriProof, proof.len()),
+        ],
+        |bytes| bytes.write_proof(&decoded),
+    )
+    .map_err(Failure::non_canonical_proof)?;
 
-    ProofWithPublicInputs::<F, C, D>::from_bytes(bytes, common_data)
-        .map_err(|_| DeserializeError::InvalidProof)
+    Ok(ProofWithPublicInputs {
+        proof: decoded,
+        public_inputs,
+    })
 }
 
-/// Combine a compressed `Proof` and `Pubs` and deserialize into `CompressedProofWithPublicInputs`.
+/// Deserialize a compressed `Proof` and `Pubs` into `CompressedProofWithPublicInputs`.
 pub fn deserialize_compressed_proof_with_pubs<F, C, const D: usize>(
     proof: &[u8],
     pubs: &[u8],
@@ -63,14 +468,130 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    let offset = size_of::<u64>();
-    if pubs.len() < offset {
-        return Err(DeserializeError::InvalidProof);
+    let public_inputs = deserialize_circuit_pubs(pubs, common_data)?;
+    let mut buffer = Buffer::new(proof);
+    let [wires_cap, plonk_zs_partial_products_cap, quotient_polys_cap] =
+        read_caps::<F, C, D>(&mut buffer, common_data).map_err(|failure| failure.proof(proof))?;
+    let caps_end = buffer.pos();
+    let openings = read_component(&mut buffer, Component::OpeningSet, |buffer| {
+        buffer.read_opening_set::<F, C, D>(common_data)
+    })
+    .map_err(|failure| failure.proof(proof))?;
+    let openings_end = buffer.pos();
+    let opening_proof = read_component(&mut buffer, Component::FriProof, |buffer| {
+        buffer.read_compressed_fri_proof::<F, C, D>(common_data)
+    })
+    .map_err(|failure| failure.proof(proof))?;
+    ensure_consumed(&buffer, proof)?;
+
+    let decoded = CompressedProof {
+        wires_cap,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
+        openings,
+        opening_proof,
+    };
+    ensure_canonical(
+        proof,
+        &[
+            (Component::ProofBody, caps_end),
+            (Component::OpeningSet, openings_end),
+            (Component::FriProof, proof.len()),
+        ],
+        |bytes| bytes.write_compressed_proof(&decoded),
+    )
+    .map_err(Failure::non_canonical_proof)?;
+
+    Ok(CompressedProofWithPublicInputs {
+        proof: decoded,
+        public_inputs,
+    })
+}
+
+/// Ensure the whole serialized `proof` was consumed by `buffer`.
+fn ensure_consumed(buffer: &Buffer, proof: &[u8]) -> Result<(), DeserializeError> {
+    if buffer.pos() != proof.len() {
+        return Err(DeserializeError::TrailingProofBytes {
+            consumed: buffer.pos() as u64,
+            len: proof.len() as u64,
+        });
+    }
+    Ok(())
+}
+
+/// Read wires, Z's with partial products and quotient polynomials Merkle caps of a proof.
+fn read_caps<F, C, const D: usize>(
+    buffer: &mut Buffer,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<[MerkleCap<F, C::Hasher>; 3], Failure>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let cap_height = common_data.config.fri_config.cap_height;
+    let mut read_cap = || {
+        read_component(&mut *buffer, Component::ProofBody, |buffer| {
+            buffer.read_merkle_cap(cap_height)
+        })
+    };
+    Ok([read_cap()?, read_cap()?, read_cap()?])
+}
+
+/// Deserialize public inputs, encoded as a `u64` length prefix followed by
+/// little-endian field elements.
+///
+/// The length prefix must match the number of encoded elements exactly and every
+/// element must be canonical, i.e. below the field modulus.
+pub fn deserialize_pubs<F: RichField>(pubs: &[u8]) -> Result<Vec<F>, DeserializeError> {
+    let (len, elements) = pubs.split_first_chunk::<{ size_of::<u64>() }>().ok_or(
+        DeserializeError::MalformedPublicInputs {
+            len: pubs.len() as u64,
+        },
+    )?;
+    let elements = elements.chunks_exact(size_of::<u64>());
+    if !elements.remainder().is_empty() {
+        return Err(DeserializeError::MalformedPublicInputs {
+            len: pubs.len() as u64,
+        });
+    }
+    let len = u64::from_le_bytes(*len);
+    if elements.len() as u64 != len {
+        return Err(DeserializeError::PublicInputsLengthPrefixMismatch {
+            declared: len,
+            encoded: elements.len() as u64,
+        });
     }
-    let mut bytes = Vec::with_capacity(proof.len() + pubs.len());
-    bytes.extend_from_slice(proof);
-    bytes.extend_from_slice(&pubs[offset..]);
 
-    CompressedProofWithPublicInputs::<F, C, D>::from_bytes(bytes, common_data)
-        .map_err(|_| DeserializeError::InvalidProof)
+    elements
+        .enumerate()
+        .map(|(index, element)| {
+            let mut word = [0u8; size_of::<u64>()];
+            word.copy_from_slice(element);
+            let word = u64::from_le_bytes(word);
+            if word >= F::ORDER {
+                return Err(DeserializeError::NonCanonicalPublicInput {
+                    index: index as u64,
+                });
+            }
+            Ok(F::from_canonical_u64(word))
+        })
+        .collect()
+}
+
+/// Deserialize public inputs and ensure their number is the one expected by `common_data`.
+fn deserialize_circuit_pubs<F, const D: usize>(
+    pubs: &[u8],
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<Vec<F>, DeserializeError>
+where
+    F: RichField + Extendable<D>,
+{
+    let public_inputs = deserialize_pubs(pubs)?;
+    if public_inputs.len() != common_data.num_public_inputs {
+        return Err(DeserializeError::PublicInputsLengthMismatch {
+            expected: common_data.num_public_inputs as u64,
+            actual: public_inputs.len() as u64,
+        });
+    }
+    Ok(public_inputs)
 }
@@ -0,0 +1,59 @@
+//! Detection of the `Plonky2Config` serialized verification keys were produced under.
+
+use crate::config::Plonky2Config;
+use crate::deserializer::deserialize_vk;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::{KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+use snafu::Snafu;
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+/// Error detecting the `Plonky2Config` of verification key bytes.
+#[derive(Debug, Clone, Snafu)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
+pub enum DetectError {
+    /// Bytes are consistent with more than one config.
+    #[snafu(display("Verification key is consistent with more than one config"))]
+    Ambiguous,
+    /// Bytes are consistent with no config.
+    #[snafu(display("Verification key is consistent with no config"))]
+    NoMatch,
+}
+
+/// Every supported `Plonky2Config`.
+const CONFIGS: [Plonky2Config; 2] = [Plonky2Config::Keccak, Plonky2Config::Poseidon];
+
+/// Checks whether `vk` bytes decode as `VerifierCircuitData` under `config`.
+pub(crate) fn decodes_as(config: Plonky2Config, vk: &[u8]) -> bool {
+    match config {
+        Plonky2Config::Keccak => {
+            deserialize_vk::<GoldilocksField, KeccakGoldilocksConfig, 2>(vk).is_ok()
+        }
+        Plonky2Config::Poseidon => {
+            deserialize_vk::<GoldilocksField, PoseidonGoldilocksConfig, 2>(vk).is_ok()
+        }
+    }
+}
+
+/// Config other than `declared` under which `vk` bytes decode, if any.
+pub(crate) fn mismatched_config(declared: Plonky2Config, vk: &[u8]) -> Option<Plonky2Config> {
+    CONFIGS
+        .into_iter()
+        .filter(|&config| config != declared)
+        .find(|&config| decodes_as(config, vk))
+}
+
+/// Detect the `Plonky2Config` raw `VerifierCircuitData` bytes were produced under.
+///
+/// The bytes are consistent with a config if they decode as a verification key under it.
+pub fn detect_config(vk: &[u8]) -> Result<Plonky2Config, DetectError> {
+    let mut candidates = CONFIGS.into_iter().filter(|&config| decodes_as(config, vk));
+    match (candidates.next(), candidates.next()) {
+        (Some(config), None) => Ok(config),
+        (Some(_), Some(_)) => Err(DetectError::Ambiguous),
+        (None, _) => Err(DetectError::NoMatch),
+    }
+}
@@ -0,0 +1,214 @@
+//! Structured difference between two `CircuitConfig`s.
+
+use core::fmt;
+use plonky2::plonk::circuit_data::CircuitConfig;
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+
+/// Field of a `CircuitConfig`, including the fields of its `FriConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
+pub enum ConfigField {
+    /// `num_wires`.
+    NumWires,
+    /// `num_routed_wires`.
+    NumRoutedWires,
+    /// `num_constants`.
+    NumConstants,
+    /// `use_base_arithmetic_gate`.
+    UseBaseArithmeticGate,
+    /// `security_bits`.
+    SecurityBits,
+    /// `num_challenges`.
+    NumChallenges,
+    /// `zero_knowledge`.
+    ZeroKnowledge,
+    /// `max_quotient_degree_factor`.
+    MaxQuotientDegreeFactor,
+    /// `fri_config.rate_bits`.
+    FriRateBits,
+    /// `fri_config.cap_height`.
+    FriCapHeight,
+    /// `fri_config.proof_of_work_bits`.
+    FriProofOfWorkBits,
+    /// `fri_config.reduction_strategy`.
+    FriReductionStrategy,
+    /// `fri_config.num_query_rounds`.
+    FriNumQueryRounds,
+}
+
+impl fmt::Display for ConfigField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConfigField::NumWires => "num_wires",
+            ConfigField::NumRoutedWires => "num_routed_wires",
+            ConfigField::NumConstants => "num_constants",
+            ConfigField::UseBaseArithmeticGate => "use_base_arithmetic_gate",
+            ConfigField::SecurityBits => "security_bits",
+            ConfigField::NumChallenges => "num_challenges",
+            ConfigField::ZeroKnowledge => "zero_knowledge",
+            ConfigField::MaxQuotientDegreeFactor => "max_quotient_degree_factor",
+            ConfigField::FriRateBits => "fri_config.rate_bits",
+            ConfigField::FriCapHeight => "fri_config.cap_height",
+            ConfigField::FriProofOfWorkBits => "fri_config.proof_of_work_bits",
+            ConfigField::FriReductionStrategy => "fri_config.reduction_strategy",
+            ConfigField::FriNumQueryRounds => "fri_config.num_query_rounds",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Value of a `CircuitConfig` field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
+pub enum ConfigValue {
+    /// Numeric field.
+    Number(u64),
+    /// Boolean field.
+    Flag(bool),
+    /// FRI reduction strategy, in its `Debug` representation.
+    Strategy(String),
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Number(value) => write!(f, "{}", value),
+            ConfigValue::Flag(value) => write!(f, "{}", value),
+            ConfigValue::Strategy(value) => f.write_str(value),
+        }
+    }
+}
+
+/// A single differing field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
+pub struct FieldDiff {
+    /// Differing field.
+    pub field: ConfigField,
+    /// Value in the expected config.
+    pub expected: ConfigValue,
+    /// Value in the actual config.
+    pub actual: ConfigValue,
+}
+
+/// Fields in which an actual `CircuitConfig` differs from the expected one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
+pub struct ConfigDiff {
+    /// Differing fields, in declaration order.
+    pub fields: Vec<FieldDiff>,
+}
+
+impl ConfigDiff {
+    /// Compute the difference of `actual` with respect to `expected`.
+    pub fn new(expected: &CircuitConfig, actual: &CircuitConfig) -> Self {
+        let mut fields = Vec::new();
+        let mut push = |field, expected: ConfigValue, actual: ConfigValue| {
+            if expected != actual {
+                fields.push(FieldDiff {
+                    field,
+                    expected,
+                    actual,
+                });
+            }
+        };
+        let number = |value: usize| ConfigValue::Number(value as u64);
+        let strategy = |config: &CircuitConfig| {
+            ConfigValue::Strategy(format!("{:?}", config.fri_config.reduction_strategy))
+        };
+        let (e, a) = (expected, actual);
+        let (ef, af) = (&e.fri_config, &a.fri_config);
+
+        push(
+            ConfigField::NumWires,
+            number(e.num_wires),
+            number(a.num_wires),
+        );
+        push(
+            ConfigField::NumRoutedWires,
+            number(e.num_routed_wires),
+            number(a.num_routed_wires),
+        );
+        push(
+            ConfigField::NumConstants,
+            number(e.num_constants),
+            number(a.num_constants),
+        );
+        push(
+            ConfigField::UseBaseArithmeticGate,
+            ConfigValue::Flag(e.use_base_arithmetic_gate),
+            ConfigValue::Flag(a.use_base_arithmetic_gate),
+        );
+        push(
+            ConfigField::SecurityBits,
+            number(e.security_bits),
+            number(a.security_bits),
+        );
+        push(
+            ConfigField::NumChallenges,
+            number(e.num_challenges),
+            number(a.num_challenges),
+        );
+        push(
+            ConfigField::ZeroKnowledge,
+            ConfigValue::Flag(e.zero_knowledge),
+            ConfigValue::Flag(a.zero_knowledge),
+        );
+        push(
+            ConfigField::MaxQuotientDegreeFactor,
+            number(e.max_quotient_degree_factor),
+            number(a.max_quotient_degree_factor),
+        );
+        push(
+            ConfigField::FriRateBits,
+            number(ef.rate_bits),
+            number(af.rate_bits),
+        );
+        push(
+            ConfigField::FriCapHeight,
+            number(ef.cap_height),
+            number(af.cap_height),
+        );
+        push(
+            ConfigField::FriProofOfWorkBits,
+            number(ef.proof_of_work_bits as usize),
+            number(af.proof_of_work_bits as usize),
+        );
+        push(ConfigField::FriReductionStrategy, strategy(e), strategy(a));
+        push(
+            ConfigField::FriNumQueryRounds,
+            number(ef.num_query_rounds),
+            number(af.num_query_rounds),
+        );
+
+        Self { fields }
+    }
+
+    /// Whether the two configs are equal.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diff) in self.fields.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(
+                f,
+                "{}: expected {}, got {}",
+                diff.field, diff.expected, diff.actual
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+//! Recomputation of `plonky2` circuit digests.
+
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+
+/// Circuit digest of `vk` recomputed from its components, as `plonky2` circuit
+/// builder does with the default empty domain separator.
+pub(crate) fn circuit_digest<F, C, const D: usize>(
+    vk: &VerifierCircuitData<F, C, D>,
+) -> <C::Hasher as Hasher<F>>::Hash
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let domain_separator_digest = C::Hasher::hash_pad(&[]);
+    let parts = [
+        vk.verifier_only.constants_sigmas_cap.flatten(),
+        domain_separator_digest.to_vec(),
+        vec![F::from_canonical_usize(vk.common.degree_bits())],
+    ];
+    C::Hasher::hash_no_pad(&parts.concat())
+}
+
+/// Checks whether the circuit digest stored in `vk` matches its components.
+pub(crate) fn is_consistent<F, C, const D: usize>(vk: &VerifierCircuitData<F, C, D>) -> bool
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    circuit_digest(vk) == vk.verifier_only.circuit_digest
+}
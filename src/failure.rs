@@ -0,0 +1,73 @@
+//! Categories of `plonky2` verification failures.
+
+use core::fmt;
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::ToString;
+
+/// Reason why a well-formed proof failed to verify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
+pub enum FailureReason {
+    /// Merkle paths of the proof don't have the length expected by the circuit.
+    ProofShape,
+    /// Vanishing polynomial identity doesn't hold at the challenge point.
+    ///
+    /// Wrong public inputs and corrupted openings both break the identity, so they can't be
+    /// told apart.
+    VanishingPolynomial,
+    /// FRI proof-of-work witness is insufficient.
+    ProofOfWork,
+    /// Merkle path doesn't lead to the committed cap.
+    MerklePath,
+    /// FRI proof is corrupted: folded evaluations or the final polynomial don't match the
+    /// queried openings, or the query indices of a compressed proof don't match its transcript.
+    FriProof,
+    /// Proof is malformed in a way that made the verifier abort.
+    Malformed,
+    /// Any other failure.
+    Other,
+}
+
+impl FailureReason {
+    /// Categorize an error returned by the `plonky2` verifier.
+    ///
+    /// `ProofShape` and `Malformed` come from checks of this crate instead, and so does
+    /// `FriProof` for the query indices of compressed proofs. Only messages of the `plonky2`
+    /// checks listed above are recognized, anything else is [`FailureReason::Other`].
+    pub(crate) fn of(err: impl fmt::Display) -> Self {
+        let message = err.to_string();
+        if message.contains("proof of work") || message.contains("fri_pow_response") {
+            FailureReason::ProofOfWork
+        } else if message.contains("Merkle proof") {
+            FailureReason::MerklePath
+        } else if message.contains("vanishing_polys_zeta") {
+            FailureReason::VanishingPolynomial
+        } else if message.contains("old_eval") || message.contains("Final polynomial") {
+            // Folding consistency and final polynomial checks of FRI query rounds.
+            FailureReason::FriProof
+        } else {
+            FailureReason::Other
+        }
+    }
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            FailureReason::ProofShape => "unexpected Merkle path length",
+            FailureReason::VanishingPolynomial => "vanishing polynomial check failed",
+            FailureReason::ProofOfWork => "insufficient proof of work",
+            FailureReason::MerklePath => "invalid Merkle path",
+            FailureReason::FriProof => "corrupted FRI proof",
+            FailureReason::Malformed => "malformed proof",
+            FailureReason::Other => "unknown reason",
+        };
+        f.write_str(reason)
+    }
+}
@@ -0,0 +1,110 @@
+//! Verification bounded by a fuel budget.
+//!
+//! Work is charged in stages, before doing it: preparation of the verification key, decoding,
+//! replay of the transcript of compressed proofs and verification, priced with the model of
+//! [`instrument`]. As soon as a
+//! stage can't be paid the verification stops, so the budget bounds the work of a single call.
+
+use crate::instrument::{self, Meter, OpCounters, Stage};
+use crate::{Plonky2Config, PreparedVk, Proof, VerifyError, Vk};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierCircuitData};
+use plonky2::plonk::config::GenericConfig;
+
+/// Fuel charged per decoded field element.
+const DECODE_FUEL: u64 = 1;
+/// Fuel charged per field element of a prepared verification key, which is decoded,
+/// re-encoded and decoded again under the other config if that fails.
+const PREPARE_FUEL: u64 = 3 * DECODE_FUEL;
+/// Fuel charged per base field multiplication.
+const FIELD_MUL_FUEL: u64 = 1;
+/// Fuel charged per extension field multiplication, about the base field multiplications
+/// it takes.
+const EXTENSION_MUL_FUEL: u64 = 4;
+/// Fuel charged per hash permutation or compression, about the base field
+/// multiplications of a Poseidon permutation.
+const HASH_FUEL: u64 = 1000;
+
+impl OpCounters {
+    /// Fuel charged for these operations by [`verify_with_fuel`](crate::verify_with_fuel).
+    pub fn fuel(&self) -> u64 {
+        (self
+            .hash_permutations
+            .saturating_add(self.hash_compressions))
+        .saturating_mul(HASH_FUEL)
+        .saturating_add(self.field_muls.saturating_mul(FIELD_MUL_FUEL))
+        .saturating_add(self.extension_muls.saturating_mul(EXTENSION_MUL_FUEL))
+    }
+}
+
+/// Fuel charged for decoding `proof` and `pubs`.
+fn decoding(proof: &Proof, pubs: &[u8]) -> u64 {
+    ((proof.bytes.len() + pubs.len()).div_ceil(size_of::<u64>()) as u64).saturating_mul(DECODE_FUEL)
+}
+
+/// Fuel charged for preparing `vk`, including recomputing its circuit digest, which hashes
+/// fewer field elements than `vk` has, plus the domain separator.
+fn preparation(vk: &Vk) -> u64 {
+    let elements = vk.bytes.len().div_ceil(size_of::<u64>()) as u64;
+    let permutations = instrument::permutations(vk.config, elements).saturating_add(1);
+    elements
+        .saturating_mul(PREPARE_FUEL)
+        .saturating_add(permutations.saturating_mul(HASH_FUEL))
+}
+
+/// Prepare `vk` paying with `fuel`.
+pub(crate) fn prepare(vk: &Vk, fuel: &mut u64) -> Result<PreparedVk, VerifyError> {
+    charge(fuel, preparation(vk))?;
+    PreparedVk::new(vk)
+}
+
+/// Take `required` from `fuel`, leaving it untouched if there isn't enough.
+fn charge(fuel: &mut u64, required: u64) -> Result<(), VerifyError> {
+    *fuel = fuel
+        .checked_sub(required)
+        .ok_or(VerifyError::BudgetExhausted {
+            required,
+            remaining: *fuel,
+        })?;
+    Ok(())
+}
+
+/// Meter paying each stage hashed as in `config` with `fuel`.
+struct Fuel<'a> {
+    config: Plonky2Config,
+    fuel: &'a mut u64,
+}
+
+impl Meter for Fuel<'_> {
+    fn charge<F, const D: usize>(
+        &mut self,
+        common: &CommonCircuitData<F, D>,
+        stage: Stage,
+    ) -> Result<(), VerifyError>
+    where
+        F: RichField + Extendable<D>,
+    {
+        charge(
+            self.fuel,
+            instrument::model(common, self.config, stage).fuel(),
+        )
+    }
+}
+
+/// Verify `proof` with `pubs` against decoded `vk` hashed as in `config`, paying each
+/// stage with `fuel`.
+pub(crate) fn verify<F, C, const D: usize>(
+    vk: &VerifierCircuitData<F, C, D>,
+    proof: &Proof,
+    pubs: &[u8],
+    config: Plonky2Config,
+    fuel: &mut u64,
+) -> Result<(), VerifyError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    charge(fuel, decoding(proof, pubs))?;
+    crate::verify_circuit_data(vk, proof, pubs, &mut Fuel { config, fuel }).map(|_| ())
+}
@@ -0,0 +1,22 @@
+//! Conversion of panics raised by `plonky2` on malformed inputs into errors.
+//!
+//! This is a last line of defence: inputs are checked before handing them to `plonky2`, so
+//! that every malformed input known to make it panic is rejected without unwinding. Panics
+//! can only be intercepted with the `std` feature and `panic = "unwind"`, and even then
+//! they're still reported through the global panic hook, which prints them to stderr by
+//! default.
+
+/// Run `f`, returning `None` instead of propagating its panic.
+#[cfg(all(feature = "std", panic = "unwind", not(plonky2_verifier_no_catch)))]
+pub(crate) fn catch<T>(f: impl FnOnce() -> T) -> Option<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).ok()
+}
+
+/// Run `f` as is, since its panics can't be intercepted.
+///
+/// Also used when built with `--cfg plonky2_verifier_no_catch`, to check that inputs are
+/// rejected before reaching `plonky2`.
+#[cfg(not(all(feature = "std", panic = "unwind", not(plonky2_verifier_no_catch))))]
+pub(crate) fn catch<T>(f: impl FnOnce() -> T) -> Option<T> {
+    Some(f())
+}
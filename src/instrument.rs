@@ -0,0 +1,496 @@
+//! Verification instrumented with deterministic operation counters.
+//!
+//! Hashing is counted as it happens: the proof is verified with the config of the
+//! verification key wrapped in [`Counting`](crate::counting::Counting), whose hashers tally
+//! their permutations, compressions and Merkle leaves. `plonky2` doesn't expose its field
+//! arithmetic, so it's attributed with a model of the work `plonky2` does, for the stages
+//! reached and the query rounds whose Merkle paths were all hashed. Counts only depend on the
+//! verification key and on how far the proof got, never on timing or on the host.
+//!
+//! The model also prices stages in fuel before they run, see
+//! [`verify_with_fuel`](crate::verify_with_fuel), and estimates whole verifications, see
+//! [`estimate`](crate::estimate).
+
+use crate::counting::{self, ConfigHasher};
+use crate::{Plonky2Config, Proof, VerifyError};
+use core::ops::AddAssign;
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierCircuitData};
+use plonky2::plonk::config::GenericConfig;
+
+/// Field elements of a hash digest observed by the transcript, for both hashers.
+const HASH_ELEMENTS: u64 = 4;
+/// Field elements absorbed or squeezed by a single permutation of the transcript sponge.
+const SPONGE_RATE: u64 = 8;
+/// Salt elements appended to leaves of blinded polynomials of zero-knowledge circuits.
+const SALT_SIZE: u64 = 4;
+
+/// Operations performed by a verification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpCounters {
+    /// Hash permutations of leaves, public inputs and Fiat-Shamir transcript.
+    pub hash_permutations: u64,
+    /// Two-to-one hash compressions along Merkle paths.
+    pub hash_compressions: u64,
+    /// Merkle paths verified, or rebuilt while decompressing compressed proofs.
+    pub merkle_paths: u64,
+    /// Base field multiplications.
+    pub field_muls: u64,
+    /// Extension field multiplications.
+    pub extension_muls: u64,
+    /// FRI folding steps.
+    pub fri_folds: u64,
+}
+
+impl OpCounters {
+    /// These operations repeated `times`.
+    fn scaled(self, times: u64) -> Self {
+        Self {
+            hash_permutations: self.hash_permutations.saturating_mul(times),
+            hash_compressions: self.hash_compressions.saturating_mul(times),
+            merkle_paths: self.merkle_paths.saturating_mul(times),
+            field_muls: self.field_muls.saturating_mul(times),
+            extension_muls: self.extension_muls.saturating_mul(times),
+            fri_folds: self.fri_folds.saturating_mul(times),
+        }
+    }
+
+    /// Only the arithmetic of these operations.
+    fn arithmetic(self) -> Self {
+        Self {
+            field_muls: self.field_muls,
+            extension_muls: self.extension_muls,
+            fri_folds: self.fri_folds,
+            ..Self::default()
+        }
+    }
+
+    /// Count hashing a leaf of `len` field elements and its path from `height` up to the
+    /// cap of `cap_height`, with the hasher of `config`.
+    fn hash_path(&mut self, config: Plonky2Config, len: u64, height: u64, cap_height: u64) {
+        // Leaves no larger than a digest are used as is.
+        if len.saturating_mul(size_of::<u64>() as u64) > hash_size(config) {
+            self.hash_permutations = self
+                .hash_permutations
+                .saturating_add(permutations(config, len));
+        }
+        self.hash_compressions = self
+            .hash_compressions
+            .saturating_add(height.saturating_sub(cap_height));
+    }
+}
+
+impl AddAssign for OpCounters {
+    fn add_assign(&mut self, other: Self) {
+        self.hash_permutations = self
+            .hash_permutations
+            .saturating_add(other.hash_permutations);
+        self.hash_compressions = self
+            .hash_compressions
+            .saturating_add(other.hash_compressions);
+        self.merkle_paths = self.merkle_paths.saturating_add(other.merkle_paths);
+        self.field_muls = self.field_muls.saturating_add(other.field_muls);
+        self.extension_muls = self.extension_muls.saturating_add(other.extension_muls);
+        self.fri_folds = self.fri_folds.saturating_add(other.fri_folds);
+    }
+}
+
+/// Stage of a verification, metered right before running it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Stage {
+    /// Replay of the transcript of a compressed proof, checking its query indices.
+    Replay,
+    /// `plonky2` verification, which decompresses compressed proofs first.
+    Verification {
+        /// Whether the proof is compressed.
+        compressed: bool,
+    },
+}
+
+/// Accounts for the stages of a verification before they run.
+pub(crate) trait Meter {
+    /// Account for `stage` of verifying a proof of the circuit described by `common`,
+    /// failing to stop the verification before the stage runs.
+    fn charge<F, const D: usize>(
+        &mut self,
+        common: &CommonCircuitData<F, D>,
+        stage: Stage,
+    ) -> Result<(), VerifyError>
+    where
+        F: RichField + Extendable<D>;
+}
+
+/// Verification isn't metered.
+impl Meter for () {
+    fn charge<F, const D: usize>(
+        &mut self,
+        _common: &CommonCircuitData<F, D>,
+        _stage: Stage,
+    ) -> Result<(), VerifyError>
+    where
+        F: RichField + Extendable<D>,
+    {
+        Ok(())
+    }
+}
+
+/// Meter recording whether the `plonky2` verification stage was reached.
+#[derive(Default)]
+struct Reached {
+    verification: bool,
+}
+
+impl Meter for Reached {
+    fn charge<F, const D: usize>(
+        &mut self,
+        _common: &CommonCircuitData<F, D>,
+        stage: Stage,
+    ) -> Result<(), VerifyError>
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.verification |= matches!(stage, Stage::Verification { .. });
+        Ok(())
+    }
+}
+
+/// Verify `proof` with `pubs` against decoded `vk`, counting the operations performed.
+///
+/// Hashing is counted by running the verification with [`counting::Counting`] hashers.
+/// Arithmetic is attributed from the model: the vanishing polynomial, and the decompression
+/// of every query round for compressed proofs, once `plonky2` verification is reached, then
+/// each query round whose Merkle paths were all verified.
+pub(crate) fn verify<F, C, const D: usize>(
+    vk: &VerifierCircuitData<F, C, D>,
+    proof: &Proof,
+    pubs: &[u8],
+) -> (Result<(), VerifyError>, OpCounters)
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: ConfigHasher,
+    C::InnerHasher: ConfigHasher,
+{
+    let config = <C::Hasher as ConfigHasher>::CONFIG;
+    let vk = counting::counting(vk);
+    let mut reached = Reached::default();
+    let (result, mut counters) =
+        counting::count(|| crate::verify_circuit_data(&vk, proof, pubs, &mut reached).map(|_| ()));
+    if reached.verification {
+        let common = &vk.common;
+        let query_rounds = common.config.fri_config.num_query_rounds as u64;
+        // Initial trees, then a tree per reduction.
+        let paths_per_round =
+            4u64.saturating_add(common.fri_params.reduction_arity_bits.len() as u64);
+        let mut verified_paths = counters.merkle_paths;
+        if proof.compressed {
+            counters += decompression(common, config)
+                .arithmetic()
+                .scaled(query_rounds);
+            verified_paths =
+                verified_paths.saturating_sub(query_rounds.saturating_mul(paths_per_round));
+        }
+        counters += vanishing(common);
+        counters += query(common, config)
+            .arithmetic()
+            .scaled(verified_paths / paths_per_round);
+    }
+    (result, counters)
+}
+
+/// Operations `plonky2` is modeled to perform in `stage` of verifying a proof of the
+/// circuit described by `common`, hashed as in `config`.
+///
+/// Compressed proofs are verified by `plonky2` deriving the challenges, rebuilding the
+/// Merkle paths and inferred FRI values of every query, then verifying the decompressed
+/// proof with the same challenges.
+pub(crate) fn model<F, const D: usize>(
+    common: &CommonCircuitData<F, D>,
+    config: Plonky2Config,
+    stage: Stage,
+) -> OpCounters
+where
+    F: RichField + Extendable<D>,
+{
+    let query_rounds = common.config.fri_config.num_query_rounds as u64;
+    let mut counters = transcript(common);
+    if let Stage::Verification { compressed } = stage {
+        if compressed {
+            counters += decompression(common, config).scaled(query_rounds);
+        }
+        counters += vanishing(common);
+        counters += query(common, config).scaled(query_rounds);
+    }
+    counters
+}
+
+/// Count deriving the challenges: hashing the public inputs, then running the Fiat-Shamir
+/// transcript.
+fn transcript<F, const D: usize>(common: &CommonCircuitData<F, D>) -> OpCounters
+where
+    F: RichField + Extendable<D>,
+{
+    let config = &common.config;
+    let num_challenges = config.num_challenges as u64;
+    let extension = D as u64;
+    let cap = HASH_ELEMENTS.saturating_mul(pow2(config.fri_config.cap_height as u64));
+    // Betas and gammas, followed by the additional lookup challenges if the circuit has lookups.
+    let num_permutation_challenges = if common.num_lookup_polys != 0 {
+        num_challenges.saturating_mul(4)
+    } else {
+        num_challenges.saturating_mul(2)
+    };
+
+    let mut challenger = Challenger::default();
+    // Circuit digest and public inputs hash.
+    challenger.observe(2 * HASH_ELEMENTS);
+    challenger.observe(cap);
+    challenger.challenges(num_permutation_challenges);
+    challenger.observe(cap);
+    challenger.challenges(num_challenges);
+    challenger.observe(cap);
+    challenger.challenges(extension);
+    challenger.observe(opened(common).saturating_mul(extension));
+    // FRI alpha, then a beta for every reduction.
+    challenger.challenges(extension);
+    for _ in &common.fri_params.reduction_arity_bits {
+        challenger.observe(cap);
+        challenger.challenges(extension);
+    }
+    challenger.observe(final_poly_len(common).saturating_mul(extension));
+    // Proof-of-work witness and response, then query indices.
+    challenger.observe(1);
+    challenger.challenges(1);
+    challenger.challenges(config.fri_config.num_query_rounds as u64);
+
+    OpCounters {
+        // Public inputs are hashed with the inner hasher, Poseidon for both configs.
+        hash_permutations: permutations(Plonky2Config::Poseidon, common.num_public_inputs as u64)
+            .saturating_add(challenger.permutations),
+        ..OpCounters::default()
+    }
+}
+
+/// Count evaluating the constraints at zeta and reducing them with alphas, plus the
+/// permutation argument.
+fn vanishing<F, const D: usize>(common: &CommonCircuitData<F, D>) -> OpCounters
+where
+    F: RichField + Extendable<D>,
+{
+    let constraints = common.gates.iter().fold(0u64, |sum, gate| {
+        sum.saturating_add(gate.0.num_constraints() as u64)
+    });
+    let per_challenge = (common.num_gate_constraints as u64)
+        .saturating_add((common.config.num_routed_wires as u64).saturating_mul(2))
+        .saturating_add(common.num_partial_products as u64);
+    OpCounters {
+        extension_muls: constraints
+            .saturating_add((common.config.num_challenges as u64).saturating_mul(per_challenge)),
+        ..OpCounters::default()
+    }
+}
+
+/// Count verifying a single FRI query round.
+fn query<F, const D: usize>(common: &CommonCircuitData<F, D>, config: Plonky2Config) -> OpCounters
+where
+    F: RichField + Extendable<D>,
+{
+    let extension = D as u64;
+    let cap_height = common.config.fri_config.cap_height as u64;
+    let mut height = common.fri_params.lde_bits() as u64;
+    let mut counters = OpCounters::default();
+    for leaf in initial_leaves(common) {
+        counters.hash_path(config, leaf, height, cap_height);
+        counters.merkle_paths = counters.merkle_paths.saturating_add(1);
+        // Opened values are combined with powers of the FRI alpha.
+        counters.field_muls = counters
+            .field_muls
+            .saturating_add(leaf.saturating_mul(extension));
+    }
+    for &arity_bits in &common.fri_params.reduction_arity_bits {
+        let arity = pow2(arity_bits as u64);
+        height = height.saturating_sub(arity_bits as u64);
+        counters.hash_path(config, arity.saturating_mul(extension), height, cap_height);
+        counters.merkle_paths = counters.merkle_paths.saturating_add(1);
+        // Barycentric interpolation over the coset of the folded step.
+        counters.extension_muls = counters
+            .extension_muls
+            .saturating_add(arity.saturating_mul(2));
+        counters.fri_folds = counters.fri_folds.saturating_add(1);
+    }
+    // Final polynomial is evaluated with Horner's rule.
+    counters.extension_muls = counters
+        .extension_muls
+        .saturating_add(final_poly_len(common));
+    counters
+}
+
+/// Count decompressing a single FRI query round: rebuilding its Merkle paths and inferring
+/// the values it doesn't carry by combining and folding the opened ones.
+fn decompression<F, const D: usize>(
+    common: &CommonCircuitData<F, D>,
+    config: Plonky2Config,
+) -> OpCounters
+where
+    F: RichField + Extendable<D>,
+{
+    let extension = D as u64;
+    let cap_height = common.config.fri_config.cap_height as u64;
+    let mut height = common.fri_params.lde_bits() as u64;
+    let mut counters = OpCounters::default();
+    for leaf in initial_leaves(common) {
+        counters.hash_path(config, leaf, height, cap_height);
+        counters.merkle_paths = counters.merkle_paths.saturating_add(1);
+        counters.field_muls = counters
+            .field_muls
+            .saturating_add(leaf.saturating_mul(extension));
+    }
+    for &arity_bits in &common.fri_params.reduction_arity_bits {
+        let arity = pow2(arity_bits as u64);
+        height = height.saturating_sub(arity_bits as u64);
+        counters.hash_path(config, arity.saturating_mul(extension), height, cap_height);
+        counters.merkle_paths = counters.merkle_paths.saturating_add(1);
+        counters.extension_muls = counters
+            .extension_muls
+            .saturating_add(arity.saturating_mul(2));
+    }
+    counters
+}
+
+/// Leaf sizes of the initial Merkle trees: constants and sigmas, wires, Z's with partial
+/// products and lookups, and quotient.
+fn initial_leaves<F, const D: usize>(common: &CommonCircuitData<F, D>) -> [u64; 4]
+where
+    F: RichField + Extendable<D>,
+{
+    let config = &common.config;
+    let num_challenges = config.num_challenges as u64;
+    let salt = if common.fri_params.hiding {
+        SALT_SIZE
+    } else {
+        0
+    };
+    [
+        (common.num_constants as u64).saturating_add(config.num_routed_wires as u64),
+        (config.num_wires as u64).saturating_add(salt),
+        num_challenges
+            .saturating_mul(
+                1u64.saturating_add(common.num_partial_products as u64)
+                    .saturating_add(common.num_lookup_polys as u64),
+            )
+            .saturating_add(salt),
+        num_challenges
+            .saturating_mul(common.quotient_degree_factor as u64)
+            .saturating_add(salt),
+    ]
+}
+
+/// Number of extension field elements opened by a proof: every polynomial at zeta, then
+/// Z's and lookup polynomials at the next point.
+fn opened<F, const D: usize>(common: &CommonCircuitData<F, D>) -> u64
+where
+    F: RichField + Extendable<D>,
+{
+    let salt = if common.fri_params.hiding {
+        SALT_SIZE
+    } else {
+        0
+    };
+    let num_challenges = common.config.num_challenges as u64;
+    let at_zeta = initial_leaves(common)
+        .iter()
+        .enumerate()
+        // Constants and sigmas aren't salted.
+        .map(|(tree, &leaf)| if tree == 0 { leaf } else { leaf - salt })
+        .fold(0u64, u64::saturating_add);
+    let at_next =
+        num_challenges.saturating_mul(1u64.saturating_add(common.num_lookup_polys as u64));
+    at_zeta.saturating_add(at_next)
+}
+
+/// Number of coefficients of the final FRI polynomial.
+fn final_poly_len<F, const D: usize>(common: &CommonCircuitData<F, D>) -> u64
+where
+    F: RichField + Extendable<D>,
+{
+    let reduced = common
+        .fri_params
+        .reduction_arity_bits
+        .iter()
+        .fold(0u64, |sum, &bits| sum.saturating_add(bits as u64));
+    pow2((common.degree_bits() as u64).saturating_sub(reduced))
+}
+
+/// `2^bits`, saturating.
+fn pow2(bits: u64) -> u64 {
+    u32::try_from(bits)
+        .ok()
+        .and_then(|bits| 1u64.checked_shl(bits))
+        .unwrap_or(u64::MAX)
+}
+
+/// Size in bytes of a digest of the hasher of `config`.
+fn hash_size(config: Plonky2Config) -> u64 {
+    match config {
+        Plonky2Config::Poseidon => 32,
+        Plonky2Config::Keccak => 25,
+    }
+}
+
+/// Permutations needed to hash `len` field elements without padding.
+pub(crate) fn permutations(config: Plonky2Config, len: u64) -> u64 {
+    match config {
+        // Poseidon sponge absorbs 8 elements per permutation.
+        Plonky2Config::Poseidon => len.div_ceil(SPONGE_RATE),
+        // Keccak-256 absorbs 136 bytes per permutation, including at least a byte of padding.
+        Plonky2Config::Keccak => len
+            .saturating_mul(size_of::<u64>() as u64)
+            .saturating_add(1)
+            .div_ceil(136),
+    }
+}
+
+/// Permutations of a `plonky2` challenger, tracking only how many elements it buffers.
+///
+/// The challenger permutes when its input buffer fills up, and before squeezing a
+/// challenge if it has pending inputs or no outputs left.
+#[derive(Default)]
+struct Challenger {
+    input: u64,
+    output: u64,
+    permutations: u64,
+}
+
+impl Challenger {
+    fn observe(&mut self, elements: u64) {
+        if elements == 0 {
+            return;
+        }
+        let total = self.input.saturating_add(elements);
+        self.permutations = self.permutations.saturating_add(total / SPONGE_RATE);
+        self.input = total % SPONGE_RATE;
+        // Observing clears the outputs, which are refilled only if the inputs were just absorbed.
+        self.output = if self.input == 0 { SPONGE_RATE } else { 0 };
+    }
+
+    fn challenges(&mut self, count: u64) {
+        if count == 0 {
+            return;
+        }
+        if self.input != 0 {
+            self.permutations = self.permutations.saturating_add(1);
+            self.input = 0;
+            self.output = SPONGE_RATE;
+        }
+        let from_output = count.min(self.output);
+        let rest = count - from_output;
+        let refills = rest.div_ceil(SPONGE_RATE);
+        self.permutations = self.permutations.saturating_add(refills);
+        self.output = if refills == 0 {
+            self.output - from_output
+        } else {
+            refills * SPONGE_RATE - rest
+        };
+    }
+}
@@ -5,29 +5,74 @@
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
+mod batch;
+mod canonical;
+mod challenges;
 mod config;
+mod cost;
+mod counting;
 mod deserializer;
+mod detect;
+mod diff;
+mod digest;
+mod failure;
+mod fuel;
+mod guard;
+mod instrument;
+mod limits;
+mod policy;
+mod prepared;
 mod proof;
+mod pubs;
+pub mod sanity;
+#[cfg(feature = "scale")]
+pub mod scale;
+pub mod security;
+mod shape;
 pub mod validate;
 mod vk;
 
+use instrument::{Meter, Stage};
 use plonky2::field::extension::Extendable;
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
-use plonky2::plonk::circuit_data::CircuitConfig;
-use plonky2::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::GenericConfig;
+use sanity::Violation;
 use snafu::Snafu;
 
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "parallel")]
+pub use batch::verify_batch_parallel;
+pub use batch::{verify_batch, verify_batch_with_vk};
 pub use config::Plonky2Config;
+pub use cost::{estimate, estimate_circuit, Cost};
 pub use deserializer::{
     custom::ZKVerifyGateSerializer, deserialize_compressed_proof_with_pubs,
-    deserialize_proof_with_pubs, deserialize_vk, DeserializeError,
+    deserialize_proof_with_pubs, deserialize_pubs, deserialize_vk, Component, DeserializeError,
 };
+pub use detect::{detect_config, DetectError};
+pub use diff::{ConfigDiff, ConfigField, ConfigValue, FieldDiff};
+pub use failure::FailureReason;
+pub use instrument::OpCounters;
+pub use limits::{Limit, Limits};
+pub use policy::VerifierPolicy;
+pub use prepared::{PreparedCircuit, PreparedVk};
 pub use proof::Proof;
+pub use pubs::Pubs;
 pub use validate::ValidateResult;
 pub use vk::Vk;
 
 /// Verification error.
-#[derive(Debug, Snafu)]
+#[derive(Debug, Clone, Snafu)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
 pub enum VerifyError {
     /// Invalid data.
     #[snafu(display("Invalid data for verification: [{}]", cause))]
@@ -37,11 +82,86 @@ pub enum VerifyError {
         cause: DeserializeError,
     },
     /// Unsupported circuit config.
-    #[snafu(display("Unsupported circuit config"))]
-    UnsupportedCircuitConfig,
+    #[snafu(display("Unsupported circuit config: [{}]", diff))]
+    UnsupportedCircuitConfig {
+        /// Difference with the closest accepted config.
+        diff: ConfigDiff,
+    },
+    /// Verification key bytes were produced under a different config than declared.
+    #[snafu(display(
+        "Verification key declared as {:?} but looks like {:?}",
+        declared,
+        likely
+    ))]
+    ConfigMismatch {
+        /// Config declared in the `Vk`.
+        declared: Plonky2Config,
+        /// Config the verification key bytes decode under.
+        likely: Plonky2Config,
+    },
+    /// Verification key isn't byte-equal to its canonical re-serialization.
+    #[snafu(display("Non-canonical verification key: differs at byte {}", offset))]
+    NonCanonicalVk {
+        /// Offset of the first byte differing from the canonical encoding.
+        offset: u64,
+    },
+    /// Decoded circuit data violates internal-consistency invariants.
+    #[snafu(display(
+        "Inconsistent circuit data: {} violated invariants",
+        violations.len()
+    ))]
+    InconsistentCircuitData {
+        /// Every violated invariant.
+        violations: Vec<Violation>,
+    },
+    /// Circuit digest doesn't match the one recomputed from the verification key components.
+    #[snafu(display("Circuit digest doesn't match verification key components"))]
+    CircuitDigestMismatch,
+    /// Verification key isn't the one of the circuit with the expected digest.
+    #[snafu(display("Verification key doesn't match the expected circuit digest"))]
+    UnexpectedCircuitDigest,
+    /// Circuit uses gates outside the allowlist.
+    #[snafu(display("Forbidden gates: {:?}", gates))]
+    ForbiddenGates {
+        /// IDs of the offending gates.
+        gates: Vec<String>,
+    },
+    /// A resource limit was exceeded.
+    #[snafu(display("Limit exceeded: {} is {}, max {}", limit, value, max))]
+    LimitExceeded {
+        /// Exceeded limit.
+        limit: Limit,
+        /// Actual value.
+        value: u64,
+        /// Maximum allowed value.
+        max: u64,
+    },
+    /// Conjectured security of the circuit is below the required threshold.
+    #[snafu(display("Insufficient security: {} bits, required {}", bits, required))]
+    InsufficientSecurity {
+        /// Computed conjectured security bits.
+        bits: u64,
+        /// Required security bits.
+        required: u64,
+    },
+    /// Fuel budget ran out before the verification completed.
+    #[snafu(display(
+        "Budget exhausted: {} fuel required, {} remaining",
+        required,
+        remaining
+    ))]
+    BudgetExhausted {
+        /// Fuel required by the stage that couldn't be paid.
+        required: u64,
+        /// Fuel remaining when the stage was reached.
+        remaining: u64,
+    },
     /// Failure.
-    #[snafu(display("Failed to verify"))]
-    Failure,
+    #[snafu(display("Failed to verify: {}", reason))]
+    Failure {
+        /// Category of the failure.
+        reason: FailureReason,
+    },
 }
 
 impl From<DeserializeError> for VerifyError {
@@ -52,20 +172,149 @@ impl From<DeserializeError> for VerifyError {
 
 /// Verify `proof` with `pubs` depending on `vk` plonky2 configuration.
 pub fn verify(vk: &Vk, proof: &Proof, pubs: &[u8]) -> Result<(), VerifyError> {
-    match vk.config {
-        Plonky2Config::Keccak => {
-            const D: usize = 2;
-            type C = KeccakGoldilocksConfig;
-            type F = <C as GenericConfig<D>>::F;
+    verify_with_policy(vk, proof, pubs, &VerifierPolicy::default())
+}
 
-            verify_inner::<F, C, D>(&vk.bytes, proof, pubs)
-        }
-        Plonky2Config::Poseidon => {
-            const D: usize = 2;
-            type C = PoseidonGoldilocksConfig;
-            type F = <C as GenericConfig<D>>::F;
+/// Verify `proof` with `pubs` depending on `vk` plonky2 configuration, accepting
+/// circuit configs allowed by `policy` within its limits.
+pub fn verify_with_policy(
+    vk: &Vk,
+    proof: &Proof,
+    pubs: &[u8],
+    policy: &VerifierPolicy,
+) -> Result<(), VerifyError> {
+    verify_prepared(&PreparedVk::with_policy(vk, policy)?, proof, pubs)
+}
+
+/// Verify `proof` with `pubs` only if `vk` is the one of the circuit with `circuit_digest`.
+///
+/// The digest is compared with the one stored in `vk`, which in turn must match the one
+/// recomputed from the `vk` components with the default empty domain separator, so circuits
+/// built with `CircuitBuilder::set_domain_separator` are rejected.
+/// See [`PreparedVk::circuit_digest`].
+pub fn verify_with_circuit_digest(
+    vk: &Vk,
+    proof: &Proof,
+    pubs: &[u8],
+    circuit_digest: &[u8],
+) -> Result<(), VerifyError> {
+    verify_prepared_with_circuit_digest(&PreparedVk::new(vk)?, proof, pubs, circuit_digest)
+}
+
+/// Verify `proof` with typed `pubs` depending on `vk` plonky2 configuration.
+///
+/// On success returns the decoded public inputs as canonical `u64` values.
+pub fn verify_with_pubs(vk: &Vk, proof: &Proof, pubs: &Pubs) -> Result<Vec<u64>, VerifyError> {
+    verify_prepared_with_pubs(&PreparedVk::new(vk)?, proof, pubs)
+}
+
+/// Verify `proof` with `pubs` against an already prepared `vk`, within its limits.
+pub fn verify_prepared(vk: &PreparedVk, proof: &Proof, pubs: &[u8]) -> Result<(), VerifyError> {
+    vk.limits().check_proof(proof, pubs)?;
+    match vk.circuit() {
+        PreparedCircuit::Keccak(vk) => verify_circuit_data(vk, proof, pubs, &mut ()).map(|_| ()),
+        PreparedCircuit::Poseidon(vk) => verify_circuit_data(vk, proof, pubs, &mut ()).map(|_| ()),
+    }
+}
+
+/// Verify `proof` with `pubs` against an already prepared `vk`, only if it's the one of
+/// the circuit with `circuit_digest`.
+pub fn verify_prepared_with_circuit_digest(
+    vk: &PreparedVk,
+    proof: &Proof,
+    pubs: &[u8],
+    circuit_digest: &[u8],
+) -> Result<(), VerifyError> {
+    if !vk.has_circuit_digest(circuit_digest) {
+        return Err(VerifyError::UnexpectedCircuitDigest);
+    }
+    verify_prepared(vk, proof, pubs)
+}
+
+/// Verify `proof` with typed `pubs` against an already prepared `vk`.
+///
+/// On success returns the decoded public inputs as canonical `u64` values.
+pub fn verify_prepared_with_pubs(
+    vk: &PreparedVk,
+    proof: &Proof,
+    pubs: &Pubs,
+) -> Result<Vec<u64>, VerifyError> {
+    vk.limits().check_proof(proof, &pubs.bytes)?;
+    match vk.circuit() {
+        PreparedCircuit::Keccak(vk) => verify_circuit_data(vk, proof, &pubs.bytes, &mut ()),
+        PreparedCircuit::Poseidon(vk) => verify_circuit_data(vk, proof, &pubs.bytes, &mut ()),
+    }
+}
 
-            verify_inner::<F, C, D>(&vk.bytes, proof, pubs)
+/// Verify `proof` with `pubs` depending on `vk` plonky2 configuration, counting the
+/// operations performed.
+///
+/// Hashing is counted as `plonky2` and the replay of the transcript of compressed proofs do
+/// it, by verifying with hashers which tally their permutations, compressions and Merkle
+/// leaves. Field arithmetic can't be observed, so it's taken from the model of
+/// [`estimate`] for the stages reached and the query rounds whose Merkle paths were all
+/// verified. Counters are deterministic: they only depend on `vk` and on how far `proof`
+/// got, so they are all zero if `vk` or `proof` can't be decoded.
+///
+/// With `std` hashing is tallied per thread. Without it, the tally is shared by every
+/// thread, so concurrent instrumented verifications count each other's hashing.
+pub fn verify_instrumented(
+    vk: &Vk,
+    proof: &Proof,
+    pubs: &[u8],
+) -> (Result<(), VerifyError>, OpCounters) {
+    match PreparedVk::new(vk) {
+        Ok(vk) => verify_prepared_instrumented(&vk, proof, pubs),
+        Err(err) => (Err(err), OpCounters::default()),
+    }
+}
+
+/// Verify `proof` with `pubs` against an already prepared `vk`, counting the operations
+/// performed. See [`verify_instrumented`].
+pub fn verify_prepared_instrumented(
+    vk: &PreparedVk,
+    proof: &Proof,
+    pubs: &[u8],
+) -> (Result<(), VerifyError>, OpCounters) {
+    if let Err(exceeded) = vk.limits().check_proof(proof, pubs) {
+        return (Err(exceeded.into()), OpCounters::default());
+    }
+    match vk.circuit() {
+        PreparedCircuit::Keccak(vk) => instrument::verify(vk, proof, pubs),
+        PreparedCircuit::Poseidon(vk) => instrument::verify(vk, proof, pubs),
+    }
+}
+
+/// Verify `proof` with `pubs` depending on `vk` plonky2 configuration, doing at most
+/// `fuel` work.
+///
+/// Fuel is taken from `fuel` stage by stage, before doing the work: preparing `vk`,
+/// priced by its size, decoding, replaying the transcript of compressed proofs and
+/// verifying them, priced with the model of [`estimate`]. If a stage can't be paid,
+/// returns [`VerifyError::BudgetExhausted`] without running it and leaves the remaining
+/// fuel untouched.
+pub fn verify_with_fuel(
+    vk: &Vk,
+    proof: &Proof,
+    pubs: &[u8],
+    fuel: &mut u64,
+) -> Result<(), VerifyError> {
+    verify_prepared_with_fuel(&fuel::prepare(vk, fuel)?, proof, pubs, fuel)
+}
+
+/// Verify `proof` with `pubs` against an already prepared `vk`, doing at most `fuel`
+/// work. Preparing `vk` isn't charged, see [`verify_with_fuel`].
+pub fn verify_prepared_with_fuel(
+    vk: &PreparedVk,
+    proof: &Proof,
+    pubs: &[u8],
+    fuel: &mut u64,
+) -> Result<(), VerifyError> {
+    vk.limits().check_proof(proof, pubs)?;
+    match vk.circuit() {
+        PreparedCircuit::Keccak(vk) => fuel::verify(vk, proof, pubs, Plonky2Config::Keccak, fuel),
+        PreparedCircuit::Poseidon(vk) => {
+            fuel::verify(vk, proof, pubs, Plonky2Config::Poseidon, fuel)
         }
     }
 }
@@ -80,23 +329,79 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    let vk = deserialize_vk::<F, C, D>(vk)?;
-    if vk.common.config != CircuitConfig::standard_recursion_config() {
-        return Err(VerifyError::UnsupportedCircuitConfig);
-    }
+    let policy = VerifierPolicy::default();
+    policy.limits().check_proof(proof, pubs)?;
+    let vk = prepared::prepare_inner::<F, C, D>(vk, &policy)?;
+    verify_circuit_data(&vk, proof, pubs, &mut ()).map(|_| ())
+}
 
-    if proof.compressed {
+/// Verify `proof` with `pubs` against decoded `vk`, returning the verified public inputs.
+///
+/// Compressed proofs are verified as is, without decompressing them first. Every stage
+/// is charged to `meter` before running it.
+pub(crate) fn verify_circuit_data<F, C, const D: usize>(
+    vk: &VerifierCircuitData<F, C, D>,
+    proof: &Proof,
+    pubs: &[u8],
+    meter: &mut impl Meter,
+) -> Result<Vec<u64>, VerifyError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let compressed = proof.compressed;
+    let (public_inputs, outcome) = if compressed {
         let proof =
             deserialize_compressed_proof_with_pubs::<F, C, D>(&proof.bytes, pubs, &vk.common)?;
-        let proof = proof
-            .decompress(&vk.verifier_only.circuit_digest, &vk.common)
-            .unwrap();
-        // vk.verify_compressed(proof)
-        //     .map_err(|_| VerifyError::Failure)
-
-        vk.verify(proof).map_err(|_| VerifyError::Failure)
+        meter.charge(&vk.common, Stage::Replay)?;
+        let indices = guard::catch(|| {
+            challenges::fri_query_indices(&proof, &vk.verifier_only.circuit_digest, &vk.common)
+        });
+        if indices.as_ref() != Some(&proof.proof.opening_proof.query_round_proofs.indices) {
+            return Err(VerifyError::Failure {
+                reason: FailureReason::FriProof,
+            });
+        }
+        if !shape::is_well_formed_compressed(&proof, &vk.common) {
+            return Err(VerifyError::Failure {
+                reason: FailureReason::ProofShape,
+            });
+        }
+        meter.charge(&vk.common, Stage::Verification { compressed })?;
+        (
+            proof.public_inputs.clone(),
+            guard::catch(|| vk.verify_compressed(proof)),
+        )
     } else {
         let proof = deserialize_proof_with_pubs::<F, C, D>(&proof.bytes, pubs, &vk.common)?;
-        vk.verify(proof).map_err(|_| VerifyError::Failure)
-    }
+        if !shape::is_well_formed(&proof, &vk.common) {
+            return Err(VerifyError::Failure {
+                reason: FailureReason::ProofShape,
+            });
+        }
+        meter.charge(&vk.common, Stage::Verification { compressed })?;
+        (
+            proof.public_inputs.clone(),
+            guard::catch(|| vk.verify(proof)),
+        )
+    };
+
+    into_result(outcome)?;
+    Ok(public_inputs
+        .iter()
+        .map(PrimeField64::to_canonical_u64)
+        .collect())
+}
+
+/// Turn the `outcome` of a guarded `plonky2` call into a verification result.
+pub(crate) fn into_result<T, E: core::fmt::Display>(
+    outcome: Option<Result<T, E>>,
+) -> Result<T, VerifyError> {
+    outcome
+        .ok_or(VerifyError::Failure {
+            reason: FailureReason::Malformed,
+        })?
+        .map_err(|err| VerifyError::Failure {
+            reason: FailureReason::of(err),
+        })
 }
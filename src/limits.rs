@@ -0,0 +1,154 @@
+//! Resource limits enforced on verification keys and proofs before verifying them.
+
+use crate::deserializer::VkLayout;
+use crate::validate::ValidateError;
+use crate::{Proof, VerifyError};
+use core::fmt;
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+/// Resource limited by [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
+pub enum Limit {
+    /// Size of `Vk.bytes`.
+    VkSize,
+    /// Size of `Proof.bytes`.
+    ProofSize,
+    /// Number of public inputs.
+    PubsCount,
+    /// Degree bits of the circuit.
+    DegreeBits,
+    /// Number of FRI query rounds.
+    QueryRounds,
+    /// Height of Merkle caps.
+    CapHeight,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Limit::VkSize => "verification key size",
+            Limit::ProofSize => "proof size",
+            Limit::PubsCount => "public inputs count",
+            Limit::DegreeBits => "degree bits",
+            Limit::QueryRounds => "FRI query rounds",
+            Limit::CapHeight => "Merkle cap height",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Upper bounds on the resources a verification key and proof can demand.
+///
+/// The default limits are well above what circuits built with `plonky2` standard configs
+/// need: 1 MiB verification keys, 2 MiB proofs, 65536 public inputs, 26 degree bits,
+/// 128 query rounds and Merkle caps of height 16. See [`Limits::unbounded`] to lift them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum size of `Vk.bytes`.
+    pub max_vk_size: u64,
+    /// Maximum size of `Proof.bytes`.
+    pub max_proof_size: u64,
+    /// Maximum number of public inputs, both of circuits and of `Pubs`.
+    pub max_pubs_count: u64,
+    /// Maximum degree bits of the circuit.
+    pub max_degree_bits: u64,
+    /// Maximum number of FRI query rounds.
+    pub max_query_rounds: u64,
+    /// Maximum height of Merkle caps.
+    pub max_cap_height: u64,
+}
+
+/// A resource above its limit.
+#[derive(Debug)]
+pub(crate) struct Exceeded {
+    limit: Limit,
+    value: u64,
+    max: u64,
+}
+
+impl Limits {
+    /// Limits which never trigger.
+    pub const fn unbounded() -> Self {
+        Self {
+            max_vk_size: u64::MAX,
+            max_proof_size: u64::MAX,
+            max_pubs_count: u64::MAX,
+            max_degree_bits: u64::MAX,
+            max_query_rounds: u64::MAX,
+            max_cap_height: u64::MAX,
+        }
+    }
+
+    /// Check sizes of `proof` and `pubs` before deserializing them.
+    pub(crate) fn check_proof(&self, proof: &Proof, pubs: &[u8]) -> Result<(), Exceeded> {
+        let pubs_count = pubs.len().saturating_sub(size_of::<u64>()) / size_of::<u64>();
+        check(Limit::ProofSize, proof.bytes.len(), self.max_proof_size)?;
+        check(Limit::PubsCount, pubs_count, self.max_pubs_count)
+    }
+
+    /// Check size of `vk` before deserializing it.
+    pub(crate) fn check_vk_size(&self, vk: &[u8]) -> Result<(), Exceeded> {
+        check(Limit::VkSize, vk.len(), self.max_vk_size)
+    }
+
+    /// Check the circuit parameters of a verification key, scanned before decoding it.
+    pub(crate) fn check_vk_layout(&self, layout: &VkLayout) -> Result<(), Exceeded> {
+        check_u64(Limit::DegreeBits, layout.degree_bits, self.max_degree_bits)?;
+        check_u64(
+            Limit::PubsCount,
+            layout.num_public_inputs,
+            self.max_pubs_count,
+        )?;
+        check_u64(
+            Limit::QueryRounds,
+            layout.num_query_rounds,
+            self.max_query_rounds,
+        )?;
+        check_u64(
+            Limit::CapHeight,
+            layout.constants_sigmas_cap_height.max(layout.cap_height),
+            self.max_cap_height,
+        )
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_vk_size: 1 << 20,
+            max_proof_size: 2 << 20,
+            max_pubs_count: 1 << 16,
+            max_degree_bits: 26,
+            max_query_rounds: 128,
+            max_cap_height: 16,
+        }
+    }
+}
+
+fn check(limit: Limit, value: usize, max: u64) -> Result<(), Exceeded> {
+    check_u64(limit, value as u64, max)
+}
+
+fn check_u64(limit: Limit, value: u64, max: u64) -> Result<(), Exceeded> {
+    if value > max {
+        return Err(Exceeded { limit, value, max });
+    }
+    Ok(())
+}
+
+impl From<Exceeded> for VerifyError {
+    fn from(Exceeded { limit, value, max }: Exceeded) -> Self {
+        VerifyError::LimitExceeded { limit, value, max }
+    }
+}
+
+impl From<Exceeded> for ValidateError {
+    fn from(Exceeded { limit, value, max }: Exceeded) -> Self {
+        ValidateError::LimitExceeded { limit, value, max }
+    }
+}
@@ -0,0 +1,267 @@
+//! Policy of circuits accepted by the verifier.
+
+use crate::diff::ConfigDiff;
+use crate::digest;
+use crate::limits::Limits;
+use crate::security::circuit_security_bits;
+use crate::validate::ValidateError;
+use crate::VerifyError;
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierCircuitData};
+use plonky2::plonk::config::GenericConfig;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec, vec::Vec};
+
+/// Circuit configs accepted when validating `Vk`s and verifying proofs.
+///
+/// A config is accepted if it's in the allowlist or, when a minimum security
+/// threshold is set, if its conjectured FRI security meets it. The policy can also
+/// restrict the gates circuits use and bound the resources verification can demand
+/// with [`Limits`]. Circuit digests must match the verification key components
+/// unless the policy opts out with [`VerifierPolicy::without_circuit_digest_check`].
+/// The default policy accepts only `CircuitConfig::standard_recursion_config()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierPolicy {
+    configs: Vec<CircuitConfig>,
+    min_security_bits: Option<u64>,
+    gates: Option<Vec<String>>,
+    circuit_digest: bool,
+    limits: Limits,
+}
+
+/// Why a policy rejected a circuit.
+#[derive(Debug)]
+pub(crate) enum Rejection {
+    UnsupportedCircuitConfig { diff: ConfigDiff },
+    InsufficientSecurity { bits: u64, required: u64 },
+    ForbiddenGates { gates: Vec<String> },
+    CircuitDigestMismatch,
+}
+
+impl VerifierPolicy {
+    /// Policy accepting exactly the given `configs`.
+    pub fn new(configs: Vec<CircuitConfig>) -> Self {
+        Self {
+            configs,
+            min_security_bits: None,
+            gates: None,
+            circuit_digest: true,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Policy accepting any config with at least `bits` bits of conjectured security.
+    pub fn min_security(bits: u64) -> Self {
+        Self::new(Vec::new()).with_min_security_bits(bits)
+    }
+
+    /// Policy accepting only `CircuitConfig::standard_recursion_config()`.
+    pub fn standard() -> Self {
+        Self::new(vec![CircuitConfig::standard_recursion_config()])
+    }
+
+    /// Policy accepting the standard recursion config and its zero-knowledge variant.
+    pub fn standard_with_zk() -> Self {
+        Self::standard().with_config(CircuitConfig::standard_recursion_zk_config())
+    }
+
+    /// Policy accepting the standard recursion config and the wider ECC configs.
+    pub fn standard_with_ecc() -> Self {
+        Self::standard()
+            .with_config(CircuitConfig::standard_ecc_config())
+            .with_config(CircuitConfig::wide_ecc_config())
+    }
+
+    /// Adds `config` to the accepted ones.
+    pub fn with_config(mut self, config: CircuitConfig) -> Self {
+        if !self.configs.contains(&config) {
+            self.configs.push(config);
+        }
+        self
+    }
+
+    /// Also accepts configs not in the allowlist with at least `bits` bits of
+    /// conjectured security.
+    pub fn with_min_security_bits(mut self, bits: u64) -> Self {
+        self.min_security_bits = Some(bits);
+        self
+    }
+
+    /// Only accepts circuits whose gates are all in `gates`, given by name such as
+    /// `"ArithmeticGate"` or `"RandomAccessGate"`. By default any gate supported by
+    /// `ZKVerifyGateSerializer` is accepted.
+    pub fn with_allowed_gates<I, S>(mut self, gates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.gates = Some(gates.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Stops requiring the circuit digest stored in verification keys to match the one
+    /// recomputed from their components.
+    ///
+    /// The digest is recomputed with the empty domain separator `plonky2` uses by default,
+    /// so circuits built with `CircuitBuilder::set_domain_separator` need this policy.
+    pub fn without_circuit_digest_check(mut self) -> Self {
+        self.circuit_digest = false;
+        self
+    }
+
+    /// Enforces `limits` instead of the default ones on verification keys and proofs,
+    /// including proofs verified against a [`PreparedVk`](crate::PreparedVk) built under
+    /// this policy.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Allowlisted configs.
+    pub fn configs(&self) -> &[CircuitConfig] {
+        &self.configs
+    }
+
+    /// Minimum conjectured security bits, if any.
+    pub fn min_security_bits(&self) -> Option<u64> {
+        self.min_security_bits
+    }
+
+    /// Allowlisted gate names, if gates are restricted.
+    pub fn allowed_gates(&self) -> Option<&[String]> {
+        self.gates.as_deref()
+    }
+
+    /// Whether circuit digests must match the verification key components.
+    pub fn checks_circuit_digest(&self) -> bool {
+        self.circuit_digest
+    }
+
+    /// Enforced resource limits.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Checks whether `config` is in the allowlist of this policy.
+    pub fn accepts(&self, config: &CircuitConfig) -> bool {
+        self.configs.contains(config)
+    }
+
+    /// Checks whether decoded `vk` is accepted by this policy.
+    pub(crate) fn check<F, C, const D: usize>(
+        &self,
+        vk: &VerifierCircuitData<F, C, D>,
+    ) -> Result<(), Rejection>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        self.check_config(&vk.common)?;
+        self.check_gates(&vk.common)?;
+        if self.circuit_digest && !digest::is_consistent(vk) {
+            return Err(Rejection::CircuitDigestMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks whether all gates of the circuit described by `common` are allowlisted.
+    fn check_gates<F, const D: usize>(
+        &self,
+        common: &CommonCircuitData<F, D>,
+    ) -> Result<(), Rejection>
+    where
+        F: RichField + Extendable<D>,
+    {
+        let Some(allowed) = &self.gates else {
+            return Ok(());
+        };
+        let forbidden: Vec<String> = common
+            .gates
+            .iter()
+            .map(|gate| gate.0.id())
+            .filter(|id| !allowed.iter().any(|name| name == gate_name(id)))
+            .collect();
+        if !forbidden.is_empty() {
+            return Err(Rejection::ForbiddenGates { gates: forbidden });
+        }
+        Ok(())
+    }
+
+    /// Checks whether the config of the circuit described by `common` is accepted.
+    fn check_config<F, const D: usize>(
+        &self,
+        common: &CommonCircuitData<F, D>,
+    ) -> Result<(), Rejection>
+    where
+        F: RichField + Extendable<D>,
+    {
+        if self.accepts(&common.config) {
+            return Ok(());
+        }
+        match self.min_security_bits {
+            Some(required) => {
+                let bits = circuit_security_bits(common);
+                (bits >= required)
+                    .then_some(())
+                    .ok_or(Rejection::InsufficientSecurity { bits, required })
+            }
+            None => Err(Rejection::UnsupportedCircuitConfig {
+                diff: self.closest_diff(&common.config),
+            }),
+        }
+    }
+
+    /// Difference of `config` with the closest allowlisted config, if any.
+    fn closest_diff(&self, config: &CircuitConfig) -> ConfigDiff {
+        self.configs
+            .iter()
+            .map(|expected| ConfigDiff::new(expected, config))
+            .min_by_key(|diff| diff.fields.len())
+            .unwrap_or_default()
+    }
+}
+
+/// Name of the gate with `id`, e.g. `ArithmeticGate` for `ArithmeticGate { num_ops: 20 }`.
+fn gate_name(id: &str) -> &str {
+    id.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or(id)
+}
+
+impl Default for VerifierPolicy {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl From<Rejection> for VerifyError {
+    fn from(value: Rejection) -> Self {
+        match value {
+            Rejection::UnsupportedCircuitConfig { diff } => {
+                VerifyError::UnsupportedCircuitConfig { diff }
+            }
+            Rejection::InsufficientSecurity { bits, required } => {
+                VerifyError::InsufficientSecurity { bits, required }
+            }
+            Rejection::ForbiddenGates { gates } => VerifyError::ForbiddenGates { gates },
+            Rejection::CircuitDigestMismatch => VerifyError::CircuitDigestMismatch,
+        }
+    }
+}
+
+impl From<Rejection> for ValidateError {
+    fn from(value: Rejection) -> Self {
+        match value {
+            Rejection::UnsupportedCircuitConfig { diff } => {
+                ValidateError::UnsupportedCircuitConfig { diff }
+            }
+            Rejection::InsufficientSecurity { bits, required } => {
+                ValidateError::InsufficientSecurity { bits, required }
+            }
+            Rejection::ForbiddenGates { gates } => ValidateError::ForbiddenGates { gates },
+            Rejection::CircuitDigestMismatch => ValidateError::CircuitDigestMismatch,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Verification key for `plonky2` decoded once and reused across many verifications.
+
+use crate::config::Plonky2Config;
+use crate::deserializer::{decode_vk, scan_vk};
+use crate::{detect, digest, sanity};
+use crate::{DeserializeError, Limits, VerifierPolicy, VerifyError, Vk};
+use plonky2::field::extension::Extendable;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{
+    GenericConfig, GenericHashOut, KeccakGoldilocksConfig, PoseidonGoldilocksConfig,
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// `Vk` with already deserialized and checked `VerifierCircuitData`.
+///
+/// Building it pays the cost of parsing the verification key once, so it can be
+/// reused with [`verify_prepared`](crate::verify_prepared) for every proof of the same circuit.
+/// The [`Limits`] of the policy it was built under are kept and enforced on those proofs.
+pub struct PreparedVk {
+    circuit: PreparedCircuit,
+    limits: Limits,
+}
+
+/// Deserialized `VerifierCircuitData` of a [`PreparedVk`], depending on its plonky2
+/// configuration.
+pub enum PreparedCircuit {
+    /// Verifier circuit data for Keccak over Goldilocks config.
+    Keccak(VerifierCircuitData<GoldilocksField, KeccakGoldilocksConfig, 2>),
+    /// Verifier circuit data for Poseidon over Goldilocks config.
+    Poseidon(VerifierCircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>),
+}
+
+impl PreparedVk {
+    /// Deserialize and check `vk` depending on its plonky2 configuration, under default policy.
+    pub fn new(vk: &Vk) -> Result<Self, VerifyError> {
+        Self::with_policy(vk, &VerifierPolicy::default())
+    }
+
+    /// Deserialize and check `vk` depending on its plonky2 configuration, under `policy`.
+    ///
+    /// If `vk` bytes can't be decoded under the declared config but can under another
+    /// one, returns [`VerifyError::ConfigMismatch`].
+    pub fn with_policy(vk: &Vk, policy: &VerifierPolicy) -> Result<Self, VerifyError> {
+        let circuit = match vk.config {
+            Plonky2Config::Keccak => prepare_inner(&vk.bytes, policy).map(PreparedCircuit::Keccak),
+            Plonky2Config::Poseidon => {
+                prepare_inner(&vk.bytes, policy).map(PreparedCircuit::Poseidon)
+            }
+        };
+        let circuit = circuit.map_err(|err| match err {
+            VerifyError::InvalidData { .. } => detect::mismatched_config(vk.config, &vk.bytes)
+                .map_or(err, |likely| VerifyError::ConfigMismatch {
+                    declared: vk.config,
+                    likely,
+                }),
+            err => err,
+        })?;
+        Ok(Self {
+            circuit,
+            limits: *policy.limits(),
+        })
+    }
+
+    /// Deserialized circuit data.
+    pub fn circuit(&self) -> &PreparedCircuit {
+        &self.circuit
+    }
+
+    /// Limits enforced on the proofs verified against this `PreparedVk`.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Circuit digest stored in this `PreparedVk`, serialized as bytes.
+    pub fn circuit_digest(&self) -> Vec<u8> {
+        match &self.circuit {
+            PreparedCircuit::Keccak(vk) => vk.verifier_only.circuit_digest.to_bytes(),
+            PreparedCircuit::Poseidon(vk) => vk.verifier_only.circuit_digest.to_bytes(),
+        }
+    }
+
+    /// Checks whether this `PreparedVk` is for the circuit with `expected` digest.
+    ///
+    /// The stored digest must also match the one recomputed from the components,
+    /// so a different circuit can't claim the expected digest.
+    pub(crate) fn has_circuit_digest(&self, expected: &[u8]) -> bool {
+        match &self.circuit {
+            PreparedCircuit::Keccak(vk) => {
+                digest::is_consistent(vk) && vk.verifier_only.circuit_digest.to_bytes() == expected
+            }
+            PreparedCircuit::Poseidon(vk) => {
+                digest::is_consistent(vk) && vk.verifier_only.circuit_digest.to_bytes() == expected
+            }
+        }
+    }
+
+    /// Configuration this `PreparedVk` was built for.
+    pub fn config(&self) -> Plonky2Config {
+        match self.circuit {
+            PreparedCircuit::Keccak(_) => Plonky2Config::Keccak,
+            PreparedCircuit::Poseidon(_) => Plonky2Config::Poseidon,
+        }
+    }
+}
+
+impl TryFrom<&Vk> for PreparedVk {
+    type Error = VerifyError;
+
+    fn try_from(vk: &Vk) -> Result<Self, Self::Error> {
+        PreparedVk::new(vk)
+    }
+}
+
+/// Deserialize `vk` into `VerifierCircuitData`, ensure its circuit config is accepted by `policy`
+/// and that its circuit data is internally consistent, so `plonky2` can't index out of it.
+pub(crate) fn prepare_inner<F, C, const D: usize>(
+    vk: &[u8],
+    policy: &VerifierPolicy,
+) -> Result<VerifierCircuitData<F, C, D>, VerifyError>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    policy.limits().check_vk_size(vk)?;
+    let layout = scan_vk::<F, C, D>(vk)?;
+    policy.limits().check_vk_layout(&layout)?;
+    let decoded = decode_vk::<F, C, D>(vk, &layout).map_err(|cause| match cause {
+        DeserializeError::NonCanonicalVerificationKey { offset, .. } => {
+            VerifyError::NonCanonicalVk { offset }
+        }
+        cause => cause.into(),
+    })?;
+    policy.check(&decoded)?;
+    let violations = sanity::check_common_data(&decoded.common);
+    if !violations.is_empty() {
+        return Err(VerifyError::InconsistentCircuitData { violations });
+    }
+    Ok(decoded)
+}
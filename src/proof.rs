@@ -3,6 +3,11 @@
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
 #[cfg(feature = "std")]
 extern crate std;
 #[cfg(not(feature = "std"))]
@@ -12,7 +17,8 @@ use std::vec::Vec;
 
 /// `Proof` encapsulating compression parameter.
 #[serde_as]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
 pub struct Proof {
     /// Is `proof` compressed.
     pub compressed: bool,
@@ -0,0 +1,92 @@
+//! Public inputs for `plonky2` in a format, acceptable by `zkVerify`.
+
+use crate::deserializer::deserialize_pubs;
+use crate::DeserializeError;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::hash::hash_types::RichField;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+/// `Pubs` encapsulating public inputs of a `plonky2` proof.
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
+pub struct Pubs {
+    /// Public inputs serialized as a `u64` length prefix followed by
+    /// little-endian Goldilocks field elements.
+    #[serde_as(as = "serde_with::hex::Hex")]
+    pub bytes: Vec<u8>,
+}
+
+impl Pubs {
+    /// Encodes `Pubs` from `u64` representations of Goldilocks field elements.
+    ///
+    /// Values not below the Goldilocks modulus are reduced, so the encoding is always canonical.
+    pub fn new(elements: &[u64]) -> Self {
+        let mut bytes = Vec::with_capacity((elements.len() + 1) * size_of::<u64>());
+        bytes.extend_from_slice(&(elements.len() as u64).to_le_bytes());
+        for &element in elements {
+            let element = GoldilocksField::from_noncanonical_u64(element).to_canonical_u64();
+            bytes.extend_from_slice(&element.to_le_bytes());
+        }
+        Self { bytes }
+    }
+
+    /// Encodes `Pubs` from field elements.
+    pub fn from_fields<F: RichField>(elements: &[F]) -> Self {
+        let elements: Vec<u64> = elements.iter().map(F::to_canonical_u64).collect();
+        Self::new(&elements)
+    }
+
+    /// Decodes public inputs into canonical `u64` representations of field elements.
+    pub fn to_u64s(&self) -> Result<Vec<u64>, DeserializeError> {
+        Ok(self
+            .to_fields::<GoldilocksField>()?
+            .iter()
+            .map(PrimeField64::to_canonical_u64)
+            .collect())
+    }
+
+    /// Decodes public inputs into field elements.
+    pub fn to_fields<F: RichField>(&self) -> Result<Vec<F>, DeserializeError> {
+        deserialize_pubs(&self.bytes)
+    }
+}
+
+impl AsRef<[u8]> for Pubs {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<&[u64]> for Pubs {
+    fn from(elements: &[u64]) -> Self {
+        Pubs::new(elements)
+    }
+}
+
+#[cfg(feature = "converter")]
+impl Pubs {
+    /// Serializes the entire `Pubs` struct to a binary format.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("Serialization to bytes failed")
+    }
+
+    /// Serializes the entire `Pubs` struct to a hex-encoded string.
+    pub fn as_hex(&self) -> String {
+        hex::encode(self.as_bytes())
+    }
+}
@@ -0,0 +1,262 @@
+//! Internal-consistency checks of decoded `CommonCircuitData`.
+
+use core::fmt;
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Violated invariant of `CommonCircuitData`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, MaxEncodedLen, TypeInfo))]
+pub enum Violation {
+    /// Low-degree extension doesn't fit the two-adic subgroup of the field.
+    DegreeBitsOutOfRange {
+        /// Degree bits of the circuit.
+        degree_bits: u64,
+        /// Maximum degree bits for the FRI rate.
+        max: u64,
+    },
+    /// `fri_params` don't match the FRI config or zero-knowledge flag of the circuit config.
+    FriParamsMismatch,
+    /// FRI reductions shrink the polynomial below a single coefficient.
+    ReductionArityTooLarge {
+        /// Sum of FRI reduction arity bits.
+        arity_bits: u64,
+        /// Degree bits of the circuit.
+        degree_bits: u64,
+    },
+    /// Merkle cap is taller than the smallest Merkle tree committed to by FRI.
+    CapHeightAboveDegree {
+        /// Merkle cap height.
+        cap_height: u64,
+        /// Height of the smallest committed Merkle tree.
+        max: u64,
+    },
+    /// Number of selector indices differs from the number of gates.
+    SelectorIndicesMismatch {
+        /// Number of selector indices.
+        indices: u64,
+        /// Number of gates.
+        gates: u64,
+    },
+    /// Selector groups aren't contiguous ranges covering the whole gate list.
+    SelectorGroupsNotCovering,
+    /// Gate isn't inside the selector group it's assigned to.
+    GateOutsideSelectorGroup {
+        /// Index of the gate.
+        gate: u64,
+    },
+    /// Quotient degree factor is zero or above the configured maximum.
+    QuotientDegreeFactorOutOfRange {
+        /// Quotient degree factor.
+        factor: u64,
+        /// Configured maximum.
+        max: u64,
+    },
+    /// Gate constraints have higher degree than the quotient polynomial allows.
+    GateDegreeTooHigh {
+        /// Index of the gate.
+        gate: u64,
+        /// Degree of the gate constraints.
+        degree: u64,
+    },
+    /// Number of coset shifts `k_is` differs from the number of routed wires.
+    KIsLengthMismatch {
+        /// Number of routed wires.
+        expected: u64,
+        /// Number of coset shifts.
+        actual: u64,
+    },
+    /// More public inputs than routed wires of the whole circuit can hold.
+    TooManyPublicInputs {
+        /// Number of public inputs.
+        num_public_inputs: u64,
+    },
+    /// Lookup polynomials, selectors and tables disagree with presence of lookup gates.
+    LookupMetadataMismatch {
+        /// Whether the circuit has lookup gates.
+        lookup_gates: bool,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::DegreeBitsOutOfRange { degree_bits, max } => {
+                write!(f, "degree bits {} above {}", degree_bits, max)
+            }
+            Violation::FriParamsMismatch => f.write_str("FRI params don't match circuit config"),
+            Violation::ReductionArityTooLarge {
+                arity_bits,
+                degree_bits,
+            } => write!(
+                f,
+                "FRI reduction arity bits {} above degree bits {}",
+                arity_bits, degree_bits
+            ),
+            Violation::CapHeightAboveDegree { cap_height, max } => {
+                write!(f, "cap height {} above {}", cap_height, max)
+            }
+            Violation::SelectorIndicesMismatch { indices, gates } => {
+                write!(f, "{} selector indices for {} gates", indices, gates)
+            }
+            Violation::SelectorGroupsNotCovering => {
+                f.write_str("selector groups don't cover the gates")
+            }
+            Violation::GateOutsideSelectorGroup { gate } => {
+                write!(f, "gate {} outside its selector group", gate)
+            }
+            Violation::QuotientDegreeFactorOutOfRange { factor, max } => {
+                write!(f, "quotient degree factor {} not in 1..={}", factor, max)
+            }
+            Violation::GateDegreeTooHigh { gate, degree } => {
+                write!(f, "gate {} has too high degree {}", gate, degree)
+            }
+            Violation::KIsLengthMismatch { expected, actual } => {
+                write!(f, "{} coset shifts for {} routed wires", actual, expected)
+            }
+            Violation::TooManyPublicInputs { num_public_inputs } => {
+                write!(
+                    f,
+                    "implausible number of public inputs {}",
+                    num_public_inputs
+                )
+            }
+            Violation::LookupMetadataMismatch { lookup_gates } => write!(
+                f,
+                "lookup metadata inconsistent with {} lookup gates",
+                if *lookup_gates { "present" } else { "absent" }
+            ),
+        }
+    }
+}
+
+/// Check the internal consistency of `common`, reporting every violated invariant.
+pub fn check_common_data<F, const D: usize>(common: &CommonCircuitData<F, D>) -> Vec<Violation>
+where
+    F: RichField + Extendable<D>,
+{
+    let mut violations = Vec::new();
+    let config = &common.config;
+    let fri_params = &common.fri_params;
+    let degree_bits = common.degree_bits();
+    let num_gates = common.gates.len();
+
+    let max_degree_bits = F::TWO_ADICITY.saturating_sub(config.fri_config.rate_bits);
+    if degree_bits > max_degree_bits {
+        violations.push(Violation::DegreeBitsOutOfRange {
+            degree_bits: degree_bits as u64,
+            max: max_degree_bits as u64,
+        });
+    }
+    if fri_params.config != config.fri_config || fri_params.hiding != config.zero_knowledge {
+        violations.push(Violation::FriParamsMismatch);
+    }
+    let arity_bits = fri_params
+        .reduction_arity_bits
+        .iter()
+        .fold(0usize, |sum, &bits| sum.saturating_add(bits));
+    if arity_bits > degree_bits {
+        violations.push(Violation::ReductionArityTooLarge {
+            arity_bits: arity_bits as u64,
+            degree_bits: degree_bits as u64,
+        });
+    }
+    let max_cap_height = degree_bits
+        .saturating_add(config.fri_config.rate_bits)
+        .saturating_sub(arity_bits);
+    if config.fri_config.cap_height > max_cap_height {
+        violations.push(Violation::CapHeightAboveDegree {
+            cap_height: config.fri_config.cap_height as u64,
+            max: max_cap_height as u64,
+        });
+    }
+
+    let selectors = &common.selectors_info;
+    if selectors.selector_indices.len() != num_gates {
+        violations.push(Violation::SelectorIndicesMismatch {
+            indices: selectors.selector_indices.len() as u64,
+            gates: num_gates as u64,
+        });
+    }
+    let mut covered = 0;
+    for group in &selectors.groups {
+        if group.start != covered || group.end <= group.start {
+            break;
+        }
+        covered = group.end;
+    }
+    if covered != num_gates {
+        violations.push(Violation::SelectorGroupsNotCovering);
+    }
+    for (gate, &group) in selectors.selector_indices.iter().enumerate() {
+        if !selectors
+            .groups
+            .get(group)
+            .is_some_and(|group| group.contains(&gate))
+        {
+            violations.push(Violation::GateOutsideSelectorGroup { gate: gate as u64 });
+        }
+    }
+
+    let factor = common.quotient_degree_factor;
+    if factor == 0 || factor > config.max_quotient_degree_factor {
+        violations.push(Violation::QuotientDegreeFactorOutOfRange {
+            factor: factor as u64,
+            max: config.max_quotient_degree_factor as u64,
+        });
+    }
+    for (gate, gate_ref) in common.gates.iter().enumerate() {
+        let degree = gate_ref.0.degree();
+        if degree > factor.saturating_add(1) {
+            violations.push(Violation::GateDegreeTooHigh {
+                gate: gate as u64,
+                degree: degree as u64,
+            });
+        }
+    }
+
+    if common.k_is.len() != config.num_routed_wires {
+        violations.push(Violation::KIsLengthMismatch {
+            expected: config.num_routed_wires as u64,
+            actual: common.k_is.len() as u64,
+        });
+    }
+    let capacity = 1usize
+        .checked_shl(degree_bits as u32)
+        .map_or(usize::MAX, |rows| {
+            rows.saturating_mul(config.num_routed_wires)
+        });
+    if common.num_public_inputs > capacity {
+        violations.push(Violation::TooManyPublicInputs {
+            num_public_inputs: common.num_public_inputs as u64,
+        });
+    }
+
+    let lookup_gates = common.gates.iter().any(|gate| {
+        let id = gate.0.id();
+        id.starts_with("LookupGate") || id.starts_with("LookupTableGate")
+    });
+    let lookup_metadata = [
+        common.num_lookup_polys > 0,
+        common.num_lookup_selectors > 0,
+        !common.luts.is_empty(),
+    ];
+    if lookup_metadata
+        .iter()
+        .any(|&present| present != lookup_gates)
+    {
+        violations.push(Violation::LookupMetadataMismatch { lookup_gates });
+    }
+
+    violations
+}
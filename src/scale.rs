@@ -0,0 +1,452 @@
+//! SCALE codec variants of `Vk`, `Proof`, `Pubs` and errors with a bounded encoded length.
+//!
+//! Bounded variants of `Vk`, `Proof` and `Pubs` encode exactly as the types they wrap, but
+//! refuse to decode byte payloads longer than `MAX`, so they implement `MaxEncodedLen` and
+//! can be stored by runtimes requiring a known upper bound. Bounded errors replace the
+//! unbounded payloads of `VerifyError` and `ValidateError` with their first entry and count.
+
+use crate::sanity::Violation;
+use crate::validate::ValidateError;
+use crate::{
+    ConfigDiff, ConfigField, DeserializeError, FailureReason, Limit, Plonky2Config, Proof, Pubs,
+    VerifyError, Vk,
+};
+use codec::{Compact, Decode, Encode, Input, MaxEncodedLen, Output};
+use core::ops::Deref;
+use scale_info::build::Fields;
+use scale_info::{Path, Type, TypeInfo};
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// Decode a length-prefixed byte payload, refusing payloads longer than `max`.
+fn decode_bytes<I: Input>(input: &mut I, max: u32) -> Result<Vec<u8>, codec::Error> {
+    let len = Compact::<u32>::decode(input)?.0;
+    if len > max {
+        return Err("Payload exceeds its bound".into());
+    }
+    let mut bytes = vec![0; len as usize];
+    input.read(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Maximum encoded length of a byte payload of at most `max` bytes.
+fn max_bytes_len(max: u32) -> usize {
+    Compact::<u32>::max_encoded_len().saturating_add(max as usize)
+}
+
+/// `Vk` with at most `MAX` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundedVk<const MAX: u32>(Vk);
+
+/// `Proof` with at most `MAX` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundedProof<const MAX: u32>(Proof);
+
+/// `Pubs` with at most `MAX` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundedPubs<const MAX: u32>(Pubs);
+
+impl<const MAX: u32> TryFrom<Vk> for BoundedVk<MAX> {
+    type Error = Vk;
+
+    /// Bound `vk`, giving it back if its bytes are longer than `MAX`.
+    fn try_from(vk: Vk) -> Result<Self, Self::Error> {
+        if vk.bytes.len() > MAX as usize {
+            return Err(vk);
+        }
+        Ok(Self(vk))
+    }
+}
+
+impl<const MAX: u32> TryFrom<Proof> for BoundedProof<MAX> {
+    type Error = Proof;
+
+    /// Bound `proof`, giving it back if its bytes are longer than `MAX`.
+    fn try_from(proof: Proof) -> Result<Self, Self::Error> {
+        if proof.bytes.len() > MAX as usize {
+            return Err(proof);
+        }
+        Ok(Self(proof))
+    }
+}
+
+impl<const MAX: u32> TryFrom<Pubs> for BoundedPubs<MAX> {
+    type Error = Pubs;
+
+    /// Bound `pubs`, giving them back if their bytes are longer than `MAX`.
+    fn try_from(pubs: Pubs) -> Result<Self, Self::Error> {
+        if pubs.bytes.len() > MAX as usize {
+            return Err(pubs);
+        }
+        Ok(Self(pubs))
+    }
+}
+
+impl<const MAX: u32> BoundedVk<MAX> {
+    /// Unwrap the bounded `Vk`.
+    pub fn into_inner(self) -> Vk {
+        self.0
+    }
+}
+
+impl<const MAX: u32> BoundedProof<MAX> {
+    /// Unwrap the bounded `Proof`.
+    pub fn into_inner(self) -> Proof {
+        self.0
+    }
+}
+
+impl<const MAX: u32> BoundedPubs<MAX> {
+    /// Unwrap the bounded `Pubs`.
+    pub fn into_inner(self) -> Pubs {
+        self.0
+    }
+}
+
+impl<const MAX: u32> Deref for BoundedVk<MAX> {
+    type Target = Vk;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const MAX: u32> Deref for BoundedProof<MAX> {
+    type Target = Proof;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const MAX: u32> Deref for BoundedPubs<MAX> {
+    type Target = Pubs;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const MAX: u32> Encode for BoundedVk<MAX> {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        self.0.encode_to(dest)
+    }
+}
+
+impl<const MAX: u32> Encode for BoundedProof<MAX> {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        self.0.encode_to(dest)
+    }
+}
+
+impl<const MAX: u32> Encode for BoundedPubs<MAX> {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        self.0.encode_to(dest)
+    }
+}
+
+impl<const MAX: u32> Decode for BoundedVk<MAX> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let config = Plonky2Config::decode(input)?;
+        let bytes = decode_bytes(input, MAX)?;
+        Ok(Self(Vk { config, bytes }))
+    }
+}
+
+impl<const MAX: u32> Decode for BoundedProof<MAX> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let compressed = bool::decode(input)?;
+        let bytes = decode_bytes(input, MAX)?;
+        Ok(Self(Proof { compressed, bytes }))
+    }
+}
+
+impl<const MAX: u32> Decode for BoundedPubs<MAX> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let bytes = decode_bytes(input, MAX)?;
+        Ok(Self(Pubs { bytes }))
+    }
+}
+
+impl<const MAX: u32> MaxEncodedLen for BoundedVk<MAX> {
+    fn max_encoded_len() -> usize {
+        Plonky2Config::max_encoded_len().saturating_add(max_bytes_len(MAX))
+    }
+}
+
+impl<const MAX: u32> MaxEncodedLen for BoundedProof<MAX> {
+    fn max_encoded_len() -> usize {
+        bool::max_encoded_len().saturating_add(max_bytes_len(MAX))
+    }
+}
+
+impl<const MAX: u32> MaxEncodedLen for BoundedPubs<MAX> {
+    fn max_encoded_len() -> usize {
+        max_bytes_len(MAX)
+    }
+}
+
+impl<const MAX: u32> TypeInfo for BoundedVk<MAX> {
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("BoundedVk", module_path!()))
+            .composite(
+                Fields::named()
+                    .field(|f| f.ty::<Plonky2Config>().name("config"))
+                    .field(|f| f.ty::<Vec<u8>>().name("bytes")),
+            )
+    }
+}
+
+impl<const MAX: u32> TypeInfo for BoundedProof<MAX> {
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("BoundedProof", module_path!()))
+            .composite(
+                Fields::named()
+                    .field(|f| f.ty::<bool>().name("compressed"))
+                    .field(|f| f.ty::<Vec<u8>>().name("bytes")),
+            )
+    }
+}
+
+impl<const MAX: u32> TypeInfo for BoundedPubs<MAX> {
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("BoundedPubs", module_path!()))
+            .composite(Fields::named().field(|f| f.ty::<Vec<u8>>().name("bytes")))
+    }
+}
+
+/// `VerifyError` with a bounded encoded length.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo)]
+pub enum BoundedVerifyError {
+    /// Invalid data.
+    InvalidData {
+        /// Internal error.
+        cause: DeserializeError,
+    },
+    /// Unsupported circuit config.
+    UnsupportedCircuitConfig {
+        /// First field differing from the closest accepted config.
+        field: Option<ConfigField>,
+        /// Number of differing fields.
+        count: u64,
+    },
+    /// Verification key bytes were produced under a different config than declared.
+    ConfigMismatch {
+        /// Config declared in the `Vk`.
+        declared: Plonky2Config,
+        /// Config the verification key bytes decode under.
+        likely: Plonky2Config,
+    },
+    /// Verification key isn't byte-equal to its canonical re-serialization.
+    NonCanonicalVk {
+        /// Offset of the first byte differing from the canonical encoding.
+        offset: u64,
+    },
+    /// Decoded circuit data violates internal-consistency invariants.
+    InconsistentCircuitData {
+        /// First violated invariant.
+        violation: Option<Violation>,
+        /// Number of violated invariants.
+        count: u64,
+    },
+    /// Circuit digest doesn't match the one recomputed from the verification key components.
+    CircuitDigestMismatch,
+    /// Verification key isn't the one of the circuit with the expected digest.
+    UnexpectedCircuitDigest,
+    /// Circuit uses gates outside the allowlist.
+    ForbiddenGates {
+        /// Number of offending gates.
+        count: u64,
+    },
+    /// A resource limit was exceeded.
+    LimitExceeded {
+        /// Exceeded limit.
+        limit: Limit,
+        /// Actual value.
+        value: u64,
+        /// Maximum allowed value.
+        max: u64,
+    },
+    /// Conjectured security of the circuit is below the required threshold.
+    InsufficientSecurity {
+        /// Computed conjectured security bits.
+        bits: u64,
+        /// Required security bits.
+        required: u64,
+    },
+    /// Fuel budget ran out before the verification completed.
+    BudgetExhausted {
+        /// Fuel required by the stage that couldn't be paid.
+        required: u64,
+        /// Fuel remaining when the stage was reached.
+        remaining: u64,
+    },
+    /// Failure.
+    Failure {
+        /// Category of the failure.
+        reason: FailureReason,
+    },
+}
+
+/// `ValidateError` with a bounded encoded length.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo)]
+pub enum BoundedValidateError {
+    /// Invalid data.
+    InvalidVK {
+        /// Internal error.
+        cause: DeserializeError,
+    },
+    /// Unsupported circuit config.
+    UnsupportedCircuitConfig {
+        /// First field differing from the closest accepted config.
+        field: Option<ConfigField>,
+        /// Number of differing fields.
+        count: u64,
+    },
+    /// Verification key bytes were produced under a different config than declared.
+    ConfigMismatch {
+        /// Config declared in the `Vk`.
+        declared: Plonky2Config,
+        /// Config the verification key bytes decode under.
+        likely: Plonky2Config,
+    },
+    /// Verification key isn't byte-equal to its canonical re-serialization.
+    NonCanonicalVk {
+        /// Offset of the first byte differing from the canonical encoding.
+        offset: u64,
+    },
+    /// Decoded verification key can't be re-serialized.
+    UnencodableVk,
+    /// Decoded circuit data violates internal-consistency invariants.
+    InconsistentCircuitData {
+        /// First violated invariant.
+        violation: Option<Violation>,
+        /// Number of violated invariants.
+        count: u64,
+    },
+    /// Circuit digest doesn't match the one recomputed from the verification key components.
+    CircuitDigestMismatch,
+    /// Circuit uses gates outside the allowlist.
+    ForbiddenGates {
+        /// Number of offending gates.
+        count: u64,
+    },
+    /// A resource limit was exceeded.
+    LimitExceeded {
+        /// Exceeded limit.
+        limit: Limit,
+        /// Actual value.
+        value: u64,
+        /// Maximum allowed value.
+        max: u64,
+    },
+    /// Conjectured security of the circuit is below the required threshold.
+    InsufficientSecurity {
+        /// Computed conjectured security bits.
+        bits: u64,
+        /// Required security bits.
+        required: u64,
+    },
+}
+
+/// First differing field of `diff` and the number of differing fields.
+fn bound_diff(diff: &ConfigDiff) -> (Option<ConfigField>, u64) {
+    (
+        diff.fields.first().map(|f| f.field),
+        diff.fields.len() as u64,
+    )
+}
+
+impl From<VerifyError> for BoundedVerifyError {
+    fn from(err: VerifyError) -> Self {
+        match err {
+            VerifyError::InvalidData { cause } => Self::InvalidData { cause },
+            VerifyError::UnsupportedCircuitConfig { diff } => {
+                let (field, count) = bound_diff(&diff);
+                Self::UnsupportedCircuitConfig { field, count }
+            }
+            VerifyError::ConfigMismatch { declared, likely } => {
+                Self::ConfigMismatch { declared, likely }
+            }
+            VerifyError::NonCanonicalVk { offset } => Self::NonCanonicalVk { offset },
+            VerifyError::InconsistentCircuitData { violations } => Self::InconsistentCircuitData {
+                count: violations.len() as u64,
+                violation: violations.into_iter().next(),
+            },
+            VerifyError::CircuitDigestMismatch => Self::CircuitDigestMismatch,
+            VerifyError::UnexpectedCircuitDigest => Self::UnexpectedCircuitDigest,
+            VerifyError::ForbiddenGates { gates } => Self::ForbiddenGates {
+                count: gates.len() as u64,
+            },
+            VerifyError::LimitExceeded { limit, value, max } => {
+                Self::LimitExceeded { limit, value, max }
+            }
+            VerifyError::InsufficientSecurity { bits, required } => {
+                Self::InsufficientSecurity { bits, required }
+            }
+            VerifyError::BudgetExhausted {
+                required,
+                remaining,
+            } => Self::BudgetExhausted {
+                required,
+                remaining,
+            },
+            VerifyError::Failure { reason } => Self::Failure { reason },
+        }
+    }
+}
+
+impl From<ValidateError> for BoundedValidateError {
+    fn from(err: ValidateError) -> Self {
+        match err {
+            ValidateError::InvalidVK { cause } => Self::InvalidVK { cause },
+            ValidateError::UnsupportedCircuitConfig { diff } => {
+                let (field, count) = bound_diff(&diff);
+                Self::UnsupportedCircuitConfig { field, count }
+            }
+            ValidateError::ConfigMismatch { declared, likely } => {
+                Self::ConfigMismatch { declared, likely }
+            }
+            ValidateError::NonCanonicalVk { offset } => Self::NonCanonicalVk { offset },
+            ValidateError::UnencodableVk => Self::UnencodableVk,
+            ValidateError::InconsistentCircuitData { violations } => {
+                Self::InconsistentCircuitData {
+                    count: violations.len() as u64,
+                    violation: violations.into_iter().next(),
+                }
+            }
+            ValidateError::CircuitDigestMismatch => Self::CircuitDigestMismatch,
+            ValidateError::ForbiddenGates { gates } => Self::ForbiddenGates {
+                count: gates.len() as u64,
+            },
+            ValidateError::LimitExceeded { limit, value, max } => {
+                Self::LimitExceeded { limit, value, max }
+            }
+            ValidateError::InsufficientSecurity { bits, required } => {
+                Self::InsufficientSecurity { bits, required }
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Conjectured security level of `plonky2` FRI parameters.
+
+use plonky2::field::extension::Extendable;
+use plonky2::fri::FriConfig;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+
+/// Conjectured security bits of FRI run with `fri_config` on a circuit of
+/// `2^degree_bits` rows.
+///
+/// Under the usual FRI conjecture every query round contributes `rate_bits`
+/// bits and the proof-of-work grinding adds `proof_of_work_bits` more. A Merkle
+/// cap taller than the low-degree extension itself is degenerate and gives no
+/// security at all.
+pub fn conjectured_security_bits(fri_config: &FriConfig, degree_bits: usize) -> u64 {
+    let lde_bits = degree_bits.saturating_add(fri_config.rate_bits);
+    if fri_config.cap_height > lde_bits {
+        return 0;
+    }
+    (fri_config.rate_bits as u64)
+        .saturating_mul(fri_config.num_query_rounds as u64)
+        .saturating_add(fri_config.proof_of_work_bits as u64)
+}
+
+/// Conjectured security bits of the FRI parameters of a deserialized circuit.
+pub fn circuit_security_bits<F, const D: usize>(common: &CommonCircuitData<F, D>) -> u64
+where
+    F: RichField + Extendable<D>,
+{
+    conjectured_security_bits(&common.config.fri_config, common.degree_bits())
+}
@@ -0,0 +1,151 @@
+//! Shape of decoded proofs, checked before handing them to `plonky2`.
+//!
+//! The deserializer fixes the shape of everything but the Merkle paths, whose lengths are
+//! part of the encoding. Paths of the wrong length make `plonky2` reject uncompressed proofs
+//! and panic while decompressing compressed ones, so both are checked here instead.
+
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::GenericConfig;
+use plonky2::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+/// Heights of the Merkle trees opened by FRI queries: the initial trees first, then the
+/// tree of each reduction step.
+fn heights<F, const D: usize>(common: &CommonCircuitData<F, D>) -> impl Iterator<Item = usize> + '_
+where
+    F: RichField + Extendable<D>,
+{
+    let lde_bits = common.fri_params.lde_bits();
+    core::iter::once(lde_bits).chain(common.fri_params.reduction_arity_bits.iter().scan(
+        lde_bits,
+        |height, arity_bits| {
+            *height = height.saturating_sub(*arity_bits);
+            Some(*height)
+        },
+    ))
+}
+
+/// Checks whether every Merkle path of `proof` goes from a leaf up to the cap.
+pub(crate) fn is_well_formed<F, C, const D: usize>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    common: &CommonCircuitData<F, D>,
+) -> bool
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let cap_height = common.config.fri_config.cap_height;
+    let lde_bits = common.fri_params.lde_bits();
+    proof
+        .proof
+        .opening_proof
+        .query_round_proofs
+        .iter()
+        .all(|round| {
+            round
+                .initial_trees_proof
+                .evals_proofs
+                .iter()
+                .all(|(_, path)| path.siblings.len() + cap_height == lde_bits)
+                && round
+                    .steps
+                    .iter()
+                    .zip(heights(common).skip(1))
+                    .all(|(step, height)| step.merkle_proof.siblings.len() + cap_height == height)
+        })
+}
+
+/// Checks whether the pruned Merkle paths of compressed `proof` hold every sibling needed
+/// to decompress them for its query indices.
+///
+/// Mirrors the order in which `plonky2` decompresses the paths of a tree: layer by layer,
+/// taking a sibling from the path of a query only if no other query already provided it.
+pub(crate) fn is_well_formed_compressed<F, C, const D: usize>(
+    proof: &CompressedProofWithPublicInputs<F, C, D>,
+    common: &CommonCircuitData<F, D>,
+) -> bool
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let cap_height = common.config.fri_config.cap_height;
+    let rounds = &proof.proof.opening_proof.query_round_proofs;
+    let mut heights = heights(common);
+    let Some(lde_bits) = heights.next() else {
+        return false;
+    };
+
+    let Some(initial) = rounds
+        .indices
+        .iter()
+        .map(|index| rounds.initial_trees_proofs.get(index))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    let num_trees = initial.first().map_or(0, |tree| tree.evals_proofs.len());
+    let initial_ok = (0..num_trees).all(|tree| {
+        let siblings: Option<Vec<usize>> = initial
+            .iter()
+            .map(|proof| Some(proof.evals_proofs.get(tree)?.1.siblings.len()))
+            .collect();
+        siblings.is_some_and(|siblings| {
+            siblings_suffice(&rounds.indices, &siblings, lde_bits, cap_height)
+        })
+    });
+
+    let mut indices = rounds.indices.clone();
+    initial_ok
+        && rounds
+            .steps
+            .iter()
+            .zip(&common.fri_params.reduction_arity_bits)
+            .zip(heights)
+            .all(|((step, arity_bits), height)| {
+                indices.iter_mut().for_each(|index| *index >>= arity_bits);
+                let siblings: Option<Vec<usize>> = indices
+                    .iter()
+                    .map(|index| Some(step.get(index)?.merkle_proof.siblings.len()))
+                    .collect();
+                siblings.is_some_and(|siblings| {
+                    siblings_suffice(&indices, &siblings, height, cap_height)
+                })
+            })
+}
+
+/// Checks whether pruned paths with `siblings` siblings each are enough to rebuild the
+/// paths of the leaves at `indices` of a tree of `height` up to a cap of `cap_height`.
+fn siblings_suffice(
+    indices: &[usize],
+    siblings: &[usize],
+    height: usize,
+    cap_height: usize,
+) -> bool {
+    let (Some(layers), Some(num_leaves)) = (
+        height.checked_sub(cap_height),
+        1usize.checked_shl(height as u32),
+    ) else {
+        return false;
+    };
+    let mut seen: BTreeSet<usize> = indices.iter().map(|index| index + num_leaves).collect();
+    let mut taken = vec![0; indices.len()];
+    for layer in 0..layers {
+        for (index, taken) in indices.iter().zip(&mut taken) {
+            let node = (index + num_leaves) >> layer;
+            if seen.insert(node ^ 1) {
+                *taken += 1;
+            }
+            seen.insert(node >> 1);
+        }
+    }
+    taken
+        .iter()
+        .zip(siblings)
+        .all(|(taken, available)| taken <= available)
+}
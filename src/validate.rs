@@ -1,15 +1,25 @@
 //! Validation crate centered for plonky2-verifier.
 
-use crate::deserializer::deserialize_vk;
-use crate::{DeserializeError, Plonky2Config, Vk};
+use crate::deserializer::{decode_vk, read_vk, scan_vk};
+use crate::sanity::{self, Violation};
+use crate::{canonical, detect};
+use crate::{ConfigDiff, DeserializeError, Limit, Plonky2Config, VerifierPolicy, Vk};
 use plonky2::field::extension::Extendable;
 use plonky2::hash::hash_types::RichField;
-use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
 use snafu::Snafu;
 
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
 /// Validation error.
-#[derive(Debug, Snafu)]
+#[derive(Debug, Clone, Snafu)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
 pub enum ValidateError {
     /// Invalid data.
     #[snafu(display("Invalid data: [{}]", cause))]
@@ -19,8 +29,68 @@ pub enum ValidateError {
         cause: DeserializeError,
     },
     /// Unsupported circuit config.
-    #[snafu(display("Unsupported config"))]
-    UnsupportedCircuitConfig,
+    #[snafu(display("Unsupported config: [{}]", diff))]
+    UnsupportedCircuitConfig {
+        /// Difference with the closest accepted config.
+        diff: ConfigDiff,
+    },
+    /// Verification key bytes were produced under a different config than declared.
+    #[snafu(display(
+        "Verification key declared as {:?} but looks like {:?}",
+        declared,
+        likely
+    ))]
+    ConfigMismatch {
+        /// Config declared in the `Vk`.
+        declared: Plonky2Config,
+        /// Config the verification key bytes decode under.
+        likely: Plonky2Config,
+    },
+    /// Verification key isn't byte-equal to its canonical re-serialization.
+    #[snafu(display("Non-canonical verification key: differs at byte {}", offset))]
+    NonCanonicalVk {
+        /// Offset of the first byte differing from the canonical encoding.
+        offset: u64,
+    },
+    /// Decoded verification key can't be re-serialized with `ZKVerifyGateSerializer`.
+    #[snafu(display("Verification key can't be re-encoded"))]
+    UnencodableVk,
+    /// Decoded circuit data violates internal-consistency invariants.
+    #[snafu(display(
+        "Inconsistent circuit data: {} violated invariants",
+        violations.len()
+    ))]
+    InconsistentCircuitData {
+        /// Every violated invariant.
+        violations: Vec<Violation>,
+    },
+    /// Circuit digest doesn't match the one recomputed from the verification key components.
+    #[snafu(display("Circuit digest doesn't match verification key components"))]
+    CircuitDigestMismatch,
+    /// Circuit uses gates outside the allowlist.
+    #[snafu(display("Forbidden gates: {:?}", gates))]
+    ForbiddenGates {
+        /// IDs of the offending gates.
+        gates: Vec<String>,
+    },
+    /// A resource limit was exceeded.
+    #[snafu(display("Limit exceeded: {} is {}, max {}", limit, value, max))]
+    LimitExceeded {
+        /// Exceeded limit.
+        limit: Limit,
+        /// Actual value.
+        value: u64,
+        /// Maximum allowed value.
+        max: u64,
+    },
+    /// Conjectured security of the circuit is below the required threshold.
+    #[snafu(display("Insufficient security: {} bits, required {}", bits, required))]
+    InsufficientSecurity {
+        /// Computed conjectured security bits.
+        bits: u64,
+        /// Required security bits.
+        required: u64,
+    },
 }
 
 impl From<DeserializeError> for ValidateError {
@@ -34,34 +104,100 @@ pub type ValidateResult = Result<(), ValidateError>;
 
 /// Validate `Vk`.
 pub fn validate_vk(vk: &Vk) -> ValidateResult {
+    validate_vk_with_policy(vk, &VerifierPolicy::default())
+}
+
+/// Validate `Vk`, accepting circuit configs allowed by `policy`.
+///
+/// The decoded circuit data must be internally consistent, see
+/// [`check_common_data`](crate::sanity::check_common_data), and the circuit digest
+/// of `vk` must match the one recomputed from its components unless `policy` opts out,
+/// see [`VerifierPolicy::without_circuit_digest_check`].
+///
+/// If `vk` bytes can't be decoded under the declared config but can under another
+/// one, returns [`ValidateError::ConfigMismatch`].
+pub fn validate_vk_with_policy(vk: &Vk, policy: &VerifierPolicy) -> ValidateResult {
+    let result = match vk.config {
+        Plonky2Config::Keccak => {
+            const D: usize = 2;
+            type C = KeccakGoldilocksConfig;
+            type F = <C as GenericConfig<D>>::F;
+
+            validate_vk_inner::<F, C, D>(&vk.bytes, policy)
+        }
+        Plonky2Config::Poseidon => {
+            const D: usize = 2;
+            type C = PoseidonGoldilocksConfig;
+            type F = <C as GenericConfig<D>>::F;
+
+            validate_vk_inner::<F, C, D>(&vk.bytes, policy)
+        }
+    };
+    result.map_err(|err| match err {
+        ValidateError::InvalidVK { .. } => {
+            detect::mismatched_config(vk.config, &vk.bytes).map_or(err, |likely| {
+                ValidateError::ConfigMismatch {
+                    declared: vk.config,
+                    likely,
+                }
+            })
+        }
+        err => err,
+    })
+}
+
+fn validate_vk_inner<F, C, const D: usize>(vk: &[u8], policy: &VerifierPolicy) -> ValidateResult
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    policy.limits().check_vk_size(vk)?;
+    let layout = scan_vk::<F, C, D>(vk)?;
+    policy.limits().check_vk_layout(&layout)?;
+    let decoded = decode_vk::<F, C, D>(vk, &layout).map_err(|cause| match cause {
+        DeserializeError::NonCanonicalVerificationKey { offset, .. } => {
+            ValidateError::NonCanonicalVk { offset }
+        }
+        cause => cause.into(),
+    })?;
+    policy.check(&decoded)?;
+    let violations = sanity::check_common_data(&decoded.common);
+    if !violations.is_empty() {
+        return Err(ValidateError::InconsistentCircuitData { violations });
+    }
+    Ok(())
+}
+
+/// Canonical bytes of `vk`, i.e. its decoded `VerifierCircuitData` re-serialized
+/// with `ZKVerifyGateSerializer`.
+///
+/// Non-canonical encodings, e.g. with field elements above the modulus, are normalized
+/// rather than rejected, so the result is accepted by [`validate_vk`] even when `vk` isn't.
+/// Registries can store these to make the hash of a verification key identify its circuit.
+pub fn canonicalize_vk(vk: &Vk) -> Result<Vec<u8>, ValidateError> {
     match vk.config {
         Plonky2Config::Keccak => {
             const D: usize = 2;
             type C = KeccakGoldilocksConfig;
             type F = <C as GenericConfig<D>>::F;
 
-            validate_vk_inner::<F, C, D>(&vk.bytes)
+            canonicalize_vk_inner::<F, C, D>(&vk.bytes)
         }
         Plonky2Config::Poseidon => {
             const D: usize = 2;
             type C = PoseidonGoldilocksConfig;
             type F = <C as GenericConfig<D>>::F;
 
-            validate_vk_inner::<F, C, D>(&vk.bytes)
+            canonicalize_vk_inner::<F, C, D>(&vk.bytes)
         }
     }
 }
 
-fn validate_vk_inner<F, C, const D: usize>(vk: &[u8]) -> ValidateResult
+fn canonicalize_vk_inner<F, C, const D: usize>(vk: &[u8]) -> Result<Vec<u8>, ValidateError>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    deserialize_vk::<F, C, D>(vk)
-        .map_err(ValidateError::from)
-        .and_then(|vk| {
-            (vk.common.config == CircuitConfig::standard_recursion_config())
-                .then_some(())
-                .ok_or(ValidateError::UnsupportedCircuitConfig)
-        })
+    let decoded = read_vk::<F, C, D>(vk, &scan_vk::<F, C, D>(vk)?)?;
+    canonical::encode_vk(&decoded).ok_or(ValidateError::UnencodableVk)
 }
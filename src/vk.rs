@@ -4,6 +4,11 @@ use crate::config::Plonky2Config;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+#[cfg(feature = "scale")]
+use codec::{Decode, Encode};
+#[cfg(feature = "scale")]
+use scale_info::TypeInfo;
+
 #[cfg(feature = "std")]
 extern crate std;
 #[cfg(not(feature = "std"))]
@@ -13,7 +18,8 @@ use std::vec::Vec;
 
 /// `Vk` encapsulating configuration.
 #[serde_as]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "scale", derive(Encode, Decode, TypeInfo))]
 pub struct Vk {
     /// Configuration for this `Vk`.
     pub config: Plonky2Config,
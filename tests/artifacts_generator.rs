@@ -1,12 +1,14 @@
 use std::fs;
+use std::sync::Arc;
 
 use plonky2::field::types::Field;
+use plonky2::gates::noop::NoopGate;
 use plonky2::iop::witness::{PartialWitness, WitnessWrite};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 use plonky2::util::serialization::Write;
-use plonky2_verifier::{Plonky2Config, Proof, Vk, ZKVerifyGateSerializer};
+use plonky2_verifier::{Plonky2Config, Proof, Pubs, Vk, ZKVerifyGateSerializer};
 
 /// Fibonacci circuit, taken from plonky2 examples:
 /// https://github.com/0xPolygonZero/plonky2/blob/v0.2.3/plonky2/examples/fibonacci.rs
@@ -56,11 +58,7 @@ pub fn gen_fibonacci() {
         .write_compressed_proof(&compressed_proof.proof)
         .unwrap();
 
-    let mut pubs_bytes = Vec::new();
-    pubs_bytes.write_usize(proof.public_inputs.len()).unwrap();
-    pubs_bytes
-        .write_field_vec(proof.public_inputs.as_slice())
-        .unwrap();
+    let pubs = Pubs::from_fields(&proof.public_inputs);
 
     let vk_bytes = data
         .verifier_data()
@@ -93,5 +91,112 @@ pub fn gen_fibonacci() {
         &proof_compressed,
     )
     .unwrap();
-    fs::write("tests/artifacts/pubs.bin", pubs_bytes).unwrap();
+    fs::write("tests/artifacts/pubs.bin", pubs.bytes).unwrap();
+}
+
+/// Circuit looking up squares of small numbers in a lookup table.
+/// Returns the verification key, proof, compressed proof and public inputs, without saving them.
+pub fn gen_lookup() -> (Vk, Proof, Proof, Vec<u8>) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let squares = Arc::new((0..16u16).map(|i| (i, i * i)).collect::<Vec<_>>());
+    let table = builder.add_lookup_table_from_pairs(squares);
+    let input = builder.add_virtual_target();
+    let output = builder.add_lookup_from_index(input, table);
+    builder.register_public_input(input);
+    builder.register_public_input(output);
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(input, F::from_canonical_u16(5)).unwrap();
+
+    let data = builder.build::<C>();
+    let proof = data.prove(pw).unwrap();
+
+    let mut proof_bytes = Vec::new();
+    proof_bytes.write_proof(&proof.proof).unwrap();
+    let compressed_proof = proof
+        .clone()
+        .compress(&data.verifier_only.circuit_digest, &data.common)
+        .unwrap();
+    let mut compressed_proof_bytes = Vec::new();
+    compressed_proof_bytes
+        .write_compressed_proof(&compressed_proof.proof)
+        .unwrap();
+
+    (
+        Vk {
+            config: Plonky2Config::Poseidon,
+            bytes: data
+                .verifier_data()
+                .to_bytes(&ZKVerifyGateSerializer)
+                .unwrap(),
+        },
+        Proof {
+            compressed: false,
+            bytes: proof_bytes,
+        },
+        Proof {
+            compressed: true,
+            bytes: compressed_proof_bytes,
+        },
+        Pubs::from_fields(&proof.public_inputs).bytes,
+    )
+}
+
+/// Fibonacci circuit padded with no-op gates, so that its FRI proof has a folding step.
+/// Returns the verification key, proof and public inputs, without saving them.
+pub fn gen_folded() -> (Vk, Proof, Vec<u8>) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let initial_a = builder.add_virtual_target();
+    let initial_b = builder.add_virtual_target();
+    let mut prev_target = initial_a;
+    let mut cur_target = initial_b;
+    for _ in 0..99 {
+        let temp = builder.add(prev_target, cur_target);
+        prev_target = cur_target;
+        cur_target = temp;
+    }
+    builder.register_public_input(initial_a);
+    builder.register_public_input(initial_b);
+    builder.register_public_input(cur_target);
+    // Past 32 rows the standard config folds the FRI polynomial once.
+    for _ in 0..64 {
+        builder.add_gate(NoopGate, vec![]);
+    }
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(initial_a, F::ZERO).unwrap();
+    pw.set_target(initial_b, F::ONE).unwrap();
+
+    let data = builder.build::<C>();
+    let proof = data.prove(pw).unwrap();
+
+    let mut proof_bytes = Vec::new();
+    proof_bytes.write_proof(&proof.proof).unwrap();
+
+    (
+        Vk {
+            config: Plonky2Config::Poseidon,
+            bytes: data
+                .verifier_data()
+                .to_bytes(&ZKVerifyGateSerializer)
+                .unwrap(),
+        },
+        Proof {
+            compressed: false,
+            bytes: proof_bytes,
+        },
+        Pubs::from_fields(&proof.public_inputs).bytes,
+    )
 }
@@ -2,9 +2,29 @@
 #[path = "artifacts_generator.rs"]
 mod artifacts_generator;
 
-use plonky2_verifier::{verify, DeserializeError, Proof, VerifyError, Vk};
+use plonky2::field::types::Field;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::util::serialization::Write;
+use plonky2_verifier::sanity::{check_common_data, Violation};
+use plonky2_verifier::security::circuit_security_bits;
+use plonky2_verifier::validate::{
+    canonicalize_vk, validate_vk, validate_vk_with_policy, ValidateError,
+};
+use plonky2_verifier::{
+    deserialize_proof_with_pubs, deserialize_vk, detect_config, estimate, estimate_circuit, verify,
+    verify_batch, verify_batch_with_vk, verify_instrumented, verify_prepared,
+    verify_prepared_instrumented, verify_prepared_with_fuel, verify_with_circuit_digest,
+    verify_with_fuel, verify_with_policy, verify_with_pubs, Component, ConfigDiff, ConfigField,
+    ConfigValue, Cost, DeserializeError, DetectError, FailureReason, FieldDiff, Limit, Limits,
+    OpCounters, Plonky2Config, PreparedCircuit, PreparedVk, Proof, Pubs, VerifierPolicy,
+    VerifyError, Vk,
+};
 use rstest::*;
+use serde::Deserialize;
+use std::cell::Cell;
 use std::path::Path;
+use std::sync::Once;
 
 /// `TestData` for verification in serialized format.
 struct TestData {
@@ -87,13 +107,55 @@ fn should_not_deserialize_invalid_pubs(valid_test_data: TestData) {
         matches!(
             verify(&vk, &proof, &pubs),
             Err(VerifyError::InvalidData {
-                cause: DeserializeError::InvalidProof
+                cause: DeserializeError::PublicInputsLengthPrefixMismatch {
+                    declared: 4,
+                    encoded: 3
+                }
             })
         ),
-        "Expected an InvalidProof error when `pubs` is corrupted"
+        "Expected a PublicInputsLengthPrefixMismatch error when `pubs` is corrupted"
     );
 }
 
+#[rstest]
+fn should_reject_malformed_pubs_alike_for_compressed_and_uncompressed(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let truncated = &pubs[..pubs.len() - 1];
+    let mut extra = Pubs { bytes: pubs }.to_u64s().unwrap();
+    extra.push(0);
+    let extra = Pubs::new(&extra);
+
+    for proof in [&proof, &proof_compressed] {
+        assert!(matches!(
+            verify(&vk, proof, truncated),
+            Err(VerifyError::InvalidData {
+                cause: DeserializeError::MalformedPublicInputs { len: 31 }
+            })
+        ));
+        assert!(matches!(
+            verify(&vk, proof, &extra.bytes),
+            Err(VerifyError::InvalidData {
+                cause: DeserializeError::PublicInputsLengthMismatch {
+                    expected: 3,
+                    actual: 4
+                }
+            })
+        ));
+        assert!(matches!(
+            verify(&vk, proof, &[]),
+            Err(VerifyError::InvalidData {
+                cause: DeserializeError::MalformedPublicInputs { len: 0 }
+            })
+        ));
+    }
+}
+
 #[rstest]
 fn should_not_verify_false_proof(valid_test_data: TestData) {
     let TestData {
@@ -107,7 +169,1213 @@ fn should_not_verify_false_proof(valid_test_data: TestData) {
     proof.bytes[len - 1] = pubs.last().unwrap().wrapping_add(1);
 
     assert!(
-        matches!(verify(&vk, &proof, &pubs), Err(VerifyError::Failure { .. })),
-        "Expected a Failure error when `proof` is corrupted"
+        matches!(
+            verify(&vk, &proof, &pubs),
+            Err(VerifyError::Failure {
+                reason: FailureReason::ProofOfWork
+            })
+        ),
+        "Expected a ProofOfWork failure when `proof` is corrupted"
+    );
+}
+
+/// Offset of the opening set in the fixture proof, right after three Merkle caps.
+const OPENINGS_OFFSET: usize = 3 * 16 * 32;
+/// Offset of the first FRI query round in the fixture proof.
+const QUERY_ROUNDS_OFFSET: usize = OPENINGS_OFFSET + 257 * 16;
+
+#[rstest]
+#[case::wrong_public_input(None, Some(8), FailureReason::VanishingPolynomial)]
+#[case::corrupted_opening(Some(OPENINGS_OFFSET), None, FailureReason::VanishingPolynomial)]
+#[case::corrupted_merkle_leaf(Some(QUERY_ROUNDS_OFFSET), None, FailureReason::MerklePath)]
+fn should_report_failure_reason(
+    valid_test_data: TestData,
+    #[case] proof_byte: Option<usize>,
+    #[case] pubs_byte: Option<usize>,
+    #[case] expected: FailureReason,
+) {
+    let TestData {
+        vk,
+        mut proof,
+        mut pubs,
+        ..
+    } = valid_test_data;
+
+    if let Some(i) = proof_byte {
+        proof.bytes[i] = proof.bytes[i].wrapping_add(1);
+    }
+    if let Some(i) = pubs_byte {
+        pubs[i] = pubs[i].wrapping_add(1);
+    }
+
+    match verify(&vk, &proof, &pubs) {
+        Err(VerifyError::Failure { reason }) => assert_eq!(reason, expected),
+        _ => panic!("Expected a {expected:?} failure"),
+    }
+}
+
+#[rstest]
+fn should_report_corrupted_fri_folding() {
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<2>>::F;
+
+    let (vk, proof, pubs) = artifacts_generator::gen_folded();
+    assert!(verify(&vk, &proof, &pubs).is_ok());
+
+    let common = deserialize_vk::<F, C, 2>(&vk.bytes).unwrap().common;
+    assert!(!common.fri_params.reduction_arity_bits.is_empty());
+    let mut decoded = deserialize_proof_with_pubs::<F, C, 2>(&proof.bytes, &pubs, &common).unwrap();
+    // Folding evaluations aren't part of the transcript and are checked against the
+    // combined openings before their Merkle path.
+    for eval in &mut decoded.proof.opening_proof.query_round_proofs[0].steps[0].evals {
+        *eval = eval.double();
+    }
+    let mut bytes = Vec::new();
+    bytes.write_proof(&decoded.proof).unwrap();
+    let proof = Proof {
+        compressed: false,
+        bytes,
+    };
+
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::Failure {
+            reason: FailureReason::FriProof
+        })
+    ));
+}
+
+/// Length of the leaf of the first initial tree: 4 constants and 80 sigmas.
+const FIRST_LEAF_LEN: usize = 84 * 8;
+
+#[rstest]
+// Uncompressed paths all have 2 siblings.
+#[case::uncompressed(false, QUERY_ROUNDS_OFFSET + FIRST_LEAF_LEN)]
+// Compressed query rounds follow 28 `u32` query indices, the first pruned path has 1 sibling.
+#[case::compressed(true, QUERY_ROUNDS_OFFSET + 28 * 4 + FIRST_LEAF_LEN)]
+fn should_report_proof_shape_failure(
+    valid_test_data: TestData,
+    #[case] compressed: bool,
+    #[case] path_offset: usize,
+) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+    let mut proof = if compressed { proof_compressed } else { proof };
+
+    // Drop the last sibling of the first Merkle path.
+    let siblings = proof.bytes[path_offset] as usize;
+    proof.bytes[path_offset] -= 1;
+    let last_sibling = path_offset + 1 + (siblings - 1) * 32;
+    proof.bytes.drain(last_sibling..last_sibling + 32);
+
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::Failure {
+            reason: FailureReason::ProofShape
+        })
+    ));
+}
+
+#[rstest]
+fn should_verify_valid_proofs_with_prepared_vk(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let prepared = PreparedVk::new(&vk).expect("Failed to prepare a valid Vk");
+
+    assert_eq!(prepared.config(), vk.config);
+    assert!(verify_prepared(&prepared, &proof, &pubs).is_ok());
+    assert!(verify_prepared(&prepared, &proof_compressed, &pubs).is_ok());
+}
+
+#[rstest]
+fn should_verify_batch_with_per_item_results(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let mut invalid_pubs = pubs.clone();
+    invalid_pubs[0] = invalid_pubs[0].wrapping_add(1);
+
+    let results = verify_batch([
+        (&vk, &proof, pubs.as_slice()),
+        (&vk, &proof_compressed, pubs.as_slice()),
+        (&vk, &proof, invalid_pubs.as_slice()),
+    ]);
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_ok());
+    assert!(matches!(results[2], Err(VerifyError::InvalidData { .. })));
+
+    let results = verify_batch_with_vk(
+        &vk,
+        [
+            (&proof, invalid_pubs.as_slice()),
+            (&proof_compressed, pubs.as_slice()),
+        ],
+    );
+
+    assert!(matches!(results[0], Err(VerifyError::InvalidData { .. })));
+    assert!(results[1].is_ok());
+}
+
+#[cfg(feature = "parallel")]
+#[rstest]
+fn should_verify_batch_in_parallel_as_sequential(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let mut invalid_pubs = pubs.clone();
+    invalid_pubs[0] = invalid_pubs[0].wrapping_add(1);
+
+    let items = [
+        (&vk, &proof, invalid_pubs.as_slice()),
+        (&vk, &proof, pubs.as_slice()),
+        (&vk, &proof_compressed, pubs.as_slice()),
+        (&vk, &proof_compressed, invalid_pubs.as_slice()),
+    ];
+
+    let sequential = verify_batch(items);
+    let parallel = plonky2_verifier::verify_batch_parallel(items);
+
+    assert_eq!(parallel.len(), sequential.len());
+    for (parallel, sequential) in parallel.iter().zip(sequential.iter()) {
+        assert_eq!(parallel.is_ok(), sequential.is_ok());
+    }
+    assert!(parallel[0].is_err());
+    assert!(parallel[1].is_ok());
+    assert!(parallel[2].is_ok());
+    assert!(parallel[3].is_err());
+}
+
+#[rstest]
+fn should_verify_with_typed_pubs(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let pubs = Pubs { bytes: pubs };
+    let elements = pubs.to_u64s().expect("Failed to decode pubs");
+
+    assert_eq!(elements.len(), 3);
+    assert_eq!(&elements[..2], &[0, 1]);
+    assert_eq!(Pubs::new(&elements), pubs);
+    assert_eq!(
+        Pubs::new(&[GOLDILOCKS_ORDER, GOLDILOCKS_ORDER + 1, u64::MAX])
+            .to_u64s()
+            .unwrap(),
+        [0, 1, u64::MAX - GOLDILOCKS_ORDER]
+    );
+
+    assert_eq!(verify_with_pubs(&vk, &proof, &pubs).unwrap(), elements);
+    assert_eq!(
+        verify_with_pubs(&vk, &proof_compressed, &pubs).unwrap(),
+        elements
+    );
+}
+
+#[rstest]
+fn should_report_failed_component_on_invalid_data(valid_test_data: TestData) {
+    let TestData {
+        mut vk,
+        mut proof,
+        pubs,
+        ..
+    } = valid_test_data;
+
+    proof.bytes.truncate(100);
+    match verify(&vk, &proof, &pubs) {
+        Err(VerifyError::InvalidData {
+            cause:
+                DeserializeError::InvalidProof {
+                    component: Component::ProofBody,
+                    offset,
+                    len: 100,
+                },
+        }) => assert!(offset <= 100),
+        _ => panic!("Expected an InvalidProof error in the proof body"),
+    }
+
+    vk.bytes.truncate(vk.bytes.len() - 1);
+    let err = verify(&vk, &proof, &pubs).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            VerifyError::InvalidData {
+                cause: DeserializeError::InvalidVerificationKey { .. }
+            }
+        ),
+        "Expected an InvalidVerificationKey error when `vk` is truncated"
+    );
+}
+
+/// Malformed artifacts, replacing the valid ones where present.
+#[derive(Deserialize)]
+struct MalformedCase {
+    vk: Option<Vk>,
+    proof: Option<Proof>,
+    pubs: Option<Pubs>,
+}
+
+thread_local! {
+    /// Number of panics raised on the current thread, caught or not.
+    static PANICS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Count panics of every thread in [`PANICS`], before reporting them as usual.
+fn count_panics() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let report = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            PANICS.with(|panics| panics.set(panics.get() + 1));
+            report(info);
+        }));
+    });
+}
+
+#[rstest]
+fn should_reject_malformed_corpus_without_panicking(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+    count_panics();
+    let panics = PANICS.with(Cell::get);
+
+    for entry in std::fs::read_dir("tests/artifacts/malformed").expect("Failed to read corpus") {
+        let path = entry.expect("Failed to read corpus entry").path();
+        let data = std::fs::read_to_string(&path).expect("Failed to read corpus case");
+        let case: MalformedCase =
+            serde_json::from_str(&data).expect("Failed to deserialize JSON into corpus case");
+
+        let vk = case.vk.as_ref().unwrap_or(&vk);
+        let pubs = case
+            .pubs
+            .as_ref()
+            .map_or(pubs.as_slice(), |pubs| &pubs.bytes);
+        let proofs = match case.proof.as_ref() {
+            Some(proof) => vec![proof],
+            None => vec![&proof, &proof_compressed],
+        };
+
+        if case.vk.is_some() {
+            assert!(validate_vk(vk).is_err(), "{path:?} should not validate");
+        }
+        for proof in proofs {
+            assert!(
+                verify(vk, proof, pubs).is_err(),
+                "{path:?} should not verify"
+            );
+        }
+        assert_eq!(
+            PANICS.with(Cell::get),
+            panics,
+            "{path:?} should be rejected before making plonky2 panic"
+        );
+    }
+}
+
+#[rstest]
+fn should_reject_inconsistent_vk_before_verifying(valid_test_data: TestData) {
+    let data =
+        std::fs::read_to_string("tests/artifacts/malformed/vk_zero_quotient_degree_factor.json")
+            .expect("Failed to read corpus case");
+    let case: MalformedCase =
+        serde_json::from_str(&data).expect("Failed to deserialize JSON into corpus case");
+    let (vk, proof) = (case.vk.unwrap(), case.proof.unwrap());
+
+    // The proof matches the layout of the vk, so only the consistency check keeps
+    // `plonky2` from chunking the quotient openings by zero.
+    assert!(matches!(
+        verify(&vk, &proof, &valid_test_data.pubs),
+        Err(VerifyError::InconsistentCircuitData { violations })
+            if violations.contains(&Violation::QuotientDegreeFactorOutOfRange { factor: 0, max: 8 })
+    ));
+}
+
+#[rstest]
+fn should_verify_proofs_of_circuit_with_lookups() {
+    let (vk, proof, proof_compressed, mut pubs) = artifacts_generator::gen_lookup();
+
+    assert!(validate_vk(&vk).is_ok());
+    assert!(verify(&vk, &proof, &pubs).is_ok());
+    // Query indices are replayed with the additional lookup challenges.
+    assert!(verify(&vk, &proof_compressed, &pubs).is_ok());
+
+    pubs[16] = pubs[16].wrapping_add(1);
+    assert!(matches!(
+        verify(&vk, &proof_compressed, &pubs),
+        Err(VerifyError::Failure {
+            reason: FailureReason::FriProof
+        })
+    ));
+}
+
+#[rstest]
+fn should_not_verify_compressed_proof_with_wrong_pubs(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof_compressed,
+        mut pubs,
+        ..
+    } = valid_test_data;
+
+    pubs[8] = pubs[8].wrapping_add(1);
+
+    assert!(
+        matches!(
+            verify(&vk, &proof_compressed, &pubs),
+            Err(VerifyError::Failure {
+                reason: FailureReason::FriProof
+            })
+        ),
+        "Expected a FriProof failure when `pubs` don't match the compressed proof"
+    );
+}
+
+#[rstest]
+#[case::default(VerifierPolicy::default(), true)]
+#[case::standard_with_zk(VerifierPolicy::standard_with_zk(), true)]
+#[case::standard_with_ecc(VerifierPolicy::standard_with_ecc(), true)]
+#[case::only_zk(
+    VerifierPolicy::new(vec![CircuitConfig::standard_recursion_zk_config()]),
+    false
+)]
+#[case::empty(VerifierPolicy::new(vec![]), false)]
+fn should_apply_circuit_config_policy(
+    valid_test_data: TestData,
+    #[case] policy: VerifierPolicy,
+    #[case] accepted: bool,
+) {
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+
+    let validated = validate_vk_with_policy(&vk, &policy);
+    let verified = verify_with_policy(&vk, &proof, &pubs, &policy);
+
+    if accepted {
+        assert!(validated.is_ok());
+        assert!(verified.is_ok());
+    } else {
+        assert!(matches!(
+            validated,
+            Err(ValidateError::UnsupportedCircuitConfig { .. })
+        ));
+        assert!(matches!(
+            verified,
+            Err(VerifyError::UnsupportedCircuitConfig { .. })
+        ));
+    }
+}
+
+#[rstest]
+fn should_compute_conjectured_security_of_standard_config(valid_test_data: TestData) {
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<2>>::F;
+
+    let vk = deserialize_vk::<F, C, 2>(&valid_test_data.vk.bytes).unwrap();
+
+    assert_eq!(circuit_security_bits(&vk.common), 100);
+}
+
+#[rstest]
+#[case::met(100, true)]
+#[case::not_met(101, false)]
+fn should_apply_min_security_policy(
+    valid_test_data: TestData,
+    #[case] required: u64,
+    #[case] accepted: bool,
+) {
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+    let policy = VerifierPolicy::min_security(required);
+
+    let validated = validate_vk_with_policy(&vk, &policy);
+    let verified = verify_with_policy(&vk, &proof, &pubs, &policy);
+
+    if accepted {
+        assert!(validated.is_ok());
+        assert!(verified.is_ok());
+    } else {
+        assert!(matches!(
+            validated,
+            Err(ValidateError::InsufficientSecurity {
+                bits: 100,
+                required: 101
+            })
+        ));
+        assert!(matches!(
+            verified,
+            Err(VerifyError::InsufficientSecurity {
+                bits: 100,
+                required: 101
+            })
+        ));
+    }
+}
+
+#[rstest]
+fn should_report_config_diff_when_unsupported(valid_test_data: TestData) {
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+    let policy = VerifierPolicy::new(vec![CircuitConfig::standard_recursion_zk_config()]);
+    let expected = ConfigDiff {
+        fields: vec![FieldDiff {
+            field: ConfigField::ZeroKnowledge,
+            expected: ConfigValue::Flag(true),
+            actual: ConfigValue::Flag(false),
+        }],
+    };
+
+    match validate_vk_with_policy(&vk, &policy) {
+        Err(ValidateError::UnsupportedCircuitConfig { diff }) => assert_eq!(diff, expected),
+        other => panic!("Expected UnsupportedCircuitConfig, got {other:?}"),
+    }
+    match verify_with_policy(&vk, &proof, &pubs, &policy) {
+        Err(VerifyError::UnsupportedCircuitConfig { diff }) => assert_eq!(diff, expected),
+        other => panic!("Expected UnsupportedCircuitConfig, got {other:?}"),
+    }
+}
+
+#[rstest]
+fn should_detect_config_mismatch(valid_test_data: TestData) {
+    let TestData {
+        mut vk,
+        proof,
+        pubs,
+        ..
+    } = valid_test_data;
+    vk.config = Plonky2Config::Keccak;
+
+    assert!(matches!(
+        validate_vk(&vk),
+        Err(ValidateError::ConfigMismatch {
+            declared: Plonky2Config::Keccak,
+            likely: Plonky2Config::Poseidon
+        })
+    ));
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::ConfigMismatch {
+            declared: Plonky2Config::Keccak,
+            likely: Plonky2Config::Poseidon
+        })
+    ));
+}
+
+#[rstest]
+fn should_detect_config_from_vk_bytes(valid_test_data: TestData) {
+    let vk = valid_test_data.vk;
+
+    assert_eq!(detect_config(&vk.bytes).unwrap(), vk.config);
+    assert!(matches!(
+        detect_config(&vk.bytes[..vk.bytes.len() / 2]),
+        Err(DetectError::NoMatch)
+    ));
+}
+
+/// Goldilocks field modulus.
+const GOLDILOCKS_ORDER: u64 = 0xffff_ffff_0000_0001;
+
+/// Add the field modulus to the `u64` at `offset`, keeping it congruent but non-canonical.
+fn make_non_canonical(bytes: &mut [u8], offset: usize) {
+    let word = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+    bytes[offset..offset + 8].copy_from_slice(&(word + GOLDILOCKS_ORDER).to_le_bytes());
+}
+
+#[rstest]
+fn should_reject_trailing_bytes(valid_test_data: TestData) {
+    let TestData {
+        mut vk,
+        mut proof,
+        mut proof_compressed,
+        pubs,
+    } = valid_test_data;
+    let valid_vk = Vk {
+        bytes: vk.bytes.clone(),
+        config: vk.config,
+    };
+    vk.bytes.push(0);
+    proof.bytes.push(0);
+    proof_compressed.bytes.push(0);
+
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::InvalidData {
+            cause: DeserializeError::TrailingVerificationKeyBytes { .. }
+        })
+    ));
+    for proof in [&proof, &proof_compressed] {
+        assert!(matches!(
+            verify(&valid_vk, proof, &pubs),
+            Err(VerifyError::InvalidData {
+                cause: DeserializeError::TrailingProofBytes { consumed, len }
+            }) if consumed + 1 == len
+        ));
+    }
+}
+
+#[rstest]
+fn should_reject_non_canonical_field_elements(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        mut proof,
+        mut proof_compressed,
+        mut pubs,
+    } = valid_test_data;
+
+    // The proof-of-work witness is the last field element of a proof.
+    for proof in [&mut proof, &mut proof_compressed] {
+        let offset = proof.bytes.len() - 8;
+        make_non_canonical(&mut proof.bytes, offset);
+        assert!(matches!(
+            verify(&vk, proof, &pubs),
+            Err(VerifyError::InvalidData {
+                cause: DeserializeError::NonCanonicalProof {
+                    component: Component::FriProof,
+                    ..
+                }
+            })
+        ));
+    }
+
+    make_non_canonical(&mut pubs, 8);
+    assert!(matches!(
+        Pubs { bytes: pubs }.to_u64s(),
+        Err(DeserializeError::NonCanonicalPublicInput { index: 0 })
+    ));
+}
+
+/// Offset of the coset shifts `k_is` in the fixture vk.
+const K_IS_OFFSET: usize = 829;
+
+#[rstest]
+#[case::constants_sigmas_cap(8, Component::VerifierOnlyData)]
+#[case::coset_shift(K_IS_OFFSET + 2 * 8, Component::CommonData)]
+fn should_reject_non_canonical_vk(
+    valid_test_data: TestData,
+    #[case] offset: usize,
+    #[case] component: Component,
+) {
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<2>>::F;
+
+    let TestData {
+        mut vk,
+        proof,
+        pubs,
+        ..
+    } = valid_test_data;
+    make_non_canonical(&mut vk.bytes, offset);
+    let is_expected = |cause: &DeserializeError| {
+        matches!(
+            *cause,
+            DeserializeError::NonCanonicalVerificationKey { component: c, offset: o }
+                if c == component && o == offset as u64
+        )
+    };
+
+    assert!(deserialize_vk::<F, C, 2>(&vk.bytes)
+        .err()
+        .is_some_and(|cause| is_expected(&cause)));
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::NonCanonicalVk { offset: o }) if o == offset as u64
+    ));
+    assert!(matches!(
+        validate_vk(&vk),
+        Err(ValidateError::NonCanonicalVk { offset: o }) if o == offset as u64
+    ));
+}
+
+#[rstest]
+fn should_canonicalize_vk(valid_test_data: TestData) {
+    let canonical = valid_test_data.vk;
+    let mut vk = Vk {
+        bytes: canonical.bytes.clone(),
+        config: canonical.config,
+    };
+    assert_eq!(canonicalize_vk(&vk).unwrap(), canonical.bytes);
+
+    make_non_canonical(&mut vk.bytes, 8);
+
+    assert_eq!(canonicalize_vk(&vk).unwrap(), canonical.bytes);
+}
+
+/// Offset of the circuit digest in the fixture vk, right after the constants and sigmas cap.
+const CIRCUIT_DIGEST_OFFSET: usize = 8 + 16 * 32;
+
+#[rstest]
+fn should_reject_vk_with_inconsistent_circuit_digest(valid_test_data: TestData) {
+    let TestData {
+        mut vk,
+        proof,
+        pubs,
+        ..
+    } = valid_test_data;
+    let lenient = VerifierPolicy::default().without_circuit_digest_check();
+    assert!(validate_vk(&vk).is_ok());
+
+    vk.bytes[CIRCUIT_DIGEST_OFFSET] = vk.bytes[CIRCUIT_DIGEST_OFFSET].wrapping_add(1);
+
+    assert!(matches!(
+        validate_vk(&vk),
+        Err(ValidateError::CircuitDigestMismatch)
+    ));
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::CircuitDigestMismatch)
+    ));
+    assert!(validate_vk_with_policy(&vk, &lenient).is_ok());
+}
+
+#[rstest]
+fn should_report_every_violated_invariant(valid_test_data: TestData) {
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<2>>::F;
+
+    let mut vk = deserialize_vk::<F, C, 2>(&valid_test_data.vk.bytes).unwrap();
+    assert_eq!(check_common_data(&vk.common), vec![]);
+
+    let gates = vk.common.gates.len() as u64;
+    vk.common.num_public_inputs = usize::MAX;
+    vk.common.selectors_info.selector_indices.pop();
+    vk.common.quotient_degree_factor = 0;
+
+    let violations = check_common_data(&vk.common);
+
+    for expected in [
+        Violation::TooManyPublicInputs {
+            num_public_inputs: u64::MAX,
+        },
+        Violation::SelectorIndicesMismatch {
+            indices: gates - 1,
+            gates,
+        },
+        Violation::QuotientDegreeFactorOutOfRange { factor: 0, max: 8 },
+    ] {
+        assert!(
+            violations.contains(&expected),
+            "{expected:?} not in {violations:?}"
+        );
+    }
+}
+
+#[rstest]
+fn should_verify_only_with_expected_circuit_digest(valid_test_data: TestData) {
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+    let mut circuit_digest = vk.bytes[CIRCUIT_DIGEST_OFFSET..CIRCUIT_DIGEST_OFFSET + 32].to_vec();
+
+    assert_eq!(
+        PreparedVk::new(&vk).unwrap().circuit_digest(),
+        circuit_digest
+    );
+    assert!(verify_with_circuit_digest(&vk, &proof, &pubs, &circuit_digest).is_ok());
+
+    circuit_digest[0] = circuit_digest[0].wrapping_add(1);
+
+    assert!(matches!(
+        verify_with_circuit_digest(&vk, &proof, &pubs, &circuit_digest),
+        Err(VerifyError::UnexpectedCircuitDigest)
+    ));
+}
+
+/// Names of every gate supported by `ZKVerifyGateSerializer`.
+const ALL_GATES: [&str; 16] = [
+    "ArithmeticGate",
+    "ArithmeticExtensionGate",
+    "BaseSumGate",
+    "ConstantGate",
+    "CosetInterpolationGate",
+    "ExponentiationGate",
+    "LookupGate",
+    "LookupTableGate",
+    "MulExtensionGate",
+    "NoopGate",
+    "PoseidonMdsGate",
+    "PoseidonGate",
+    "PublicInputGate",
+    "RandomAccessGate",
+    "ReducingExtensionGate",
+    "ReducingGate",
+];
+
+#[rstest]
+fn should_apply_gate_allowlist_policy(valid_test_data: TestData) {
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+
+    let policy = VerifierPolicy::default().with_allowed_gates(ALL_GATES);
+    assert!(validate_vk_with_policy(&vk, &policy).is_ok());
+    assert!(verify_with_policy(&vk, &proof, &pubs, &policy).is_ok());
+
+    let policy = VerifierPolicy::default().with_allowed_gates(
+        ALL_GATES
+            .into_iter()
+            .filter(|&gate| gate != "PublicInputGate"),
+    );
+    match validate_vk_with_policy(&vk, &policy) {
+        Err(ValidateError::ForbiddenGates { gates }) => {
+            assert_eq!(gates.len(), 1);
+            assert!(gates[0].starts_with("PublicInputGate"));
+        }
+        other => panic!("Expected ForbiddenGates, got {other:?}"),
+    }
+    assert!(matches!(
+        verify_with_policy(&vk, &proof, &pubs, &policy),
+        Err(VerifyError::ForbiddenGates { .. })
+    ));
+}
+
+#[rstest]
+#[case::vk_size(Limit::VkSize)]
+#[case::proof_size(Limit::ProofSize)]
+#[case::pubs_count(Limit::PubsCount)]
+#[case::degree_bits(Limit::DegreeBits)]
+#[case::query_rounds(Limit::QueryRounds)]
+#[case::cap_height(Limit::CapHeight)]
+fn should_enforce_limits(valid_test_data: TestData, #[case] exceeded: Limit) {
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+    let mut limits = Limits::default();
+    match exceeded {
+        Limit::VkSize => limits.max_vk_size = vk.bytes.len() as u64 - 1,
+        Limit::ProofSize => limits.max_proof_size = proof.bytes.len() as u64 - 1,
+        Limit::PubsCount => limits.max_pubs_count = 2,
+        Limit::DegreeBits => limits.max_degree_bits = 2,
+        Limit::QueryRounds => limits.max_query_rounds = 27,
+        Limit::CapHeight => limits.max_cap_height = 3,
+    }
+    let policy = VerifierPolicy::default().with_limits(limits);
+
+    assert!(matches!(
+        verify_with_policy(&vk, &proof, &pubs, &policy),
+        Err(VerifyError::LimitExceeded { limit, .. }) if limit == exceeded
+    ));
+    if exceeded != Limit::ProofSize {
+        assert!(matches!(
+            validate_vk_with_policy(&vk, &policy),
+            Err(ValidateError::LimitExceeded { limit, .. }) if limit == exceeded
+        ));
+        return;
+    }
+
+    // Proof limits are kept in the prepared vk and enforced by every entry point.
+    let prepared = PreparedVk::with_policy(&vk, &policy).unwrap();
+    let is_exceeded = |result: Result<(), VerifyError>| matches!(result, Err(VerifyError::LimitExceeded { limit, .. }) if limit == exceeded);
+    let mut fuel = u64::MAX;
+    assert!(is_exceeded(verify_prepared(&prepared, &proof, &pubs)));
+    assert!(is_exceeded(verify_prepared_with_fuel(
+        &prepared, &proof, &pubs, &mut fuel
+    )));
+    assert_eq!(fuel, u64::MAX);
+    let (result, counters) = verify_prepared_instrumented(&prepared, &proof, &pubs);
+    assert!(is_exceeded(result));
+    assert_eq!(counters, OpCounters::default());
+}
+
+#[rstest]
+fn should_enforce_pubs_count_limit_on_pubs(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        mut pubs,
+        ..
+    } = valid_test_data;
+    let limits = Limits {
+        max_pubs_count: 3,
+        ..Limits::default()
+    };
+    let prepared =
+        PreparedVk::with_policy(&vk, &VerifierPolicy::default().with_limits(limits)).unwrap();
+
+    pubs[..8].copy_from_slice(&4u64.to_le_bytes());
+    pubs.extend_from_slice(&0u64.to_le_bytes());
+
+    assert!(matches!(
+        verify_prepared(&prepared, &proof, &pubs),
+        Err(VerifyError::LimitExceeded {
+            limit: Limit::PubsCount,
+            value: 4,
+            max: 3,
+        })
+    ));
+}
+
+#[rstest]
+fn should_bound_resources_by_default(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        mut proof,
+        pubs,
+        ..
+    } = valid_test_data;
+    assert_ne!(Limits::default(), Limits::unbounded());
+    assert_eq!(*VerifierPolicy::default().limits(), Limits::default());
+
+    proof
+        .bytes
+        .resize(Limits::default().max_proof_size as usize + 1, 0);
+
+    assert!(matches!(
+        verify(&vk, &proof, &pubs),
+        Err(VerifyError::LimitExceeded {
+            limit: Limit::ProofSize,
+            ..
+        })
+    ));
+}
+
+#[rstest]
+fn should_estimate_cost_deterministically(valid_test_data: TestData) {
+    let vk = PreparedVk::new(&valid_test_data.vk).unwrap();
+    let PreparedCircuit::Poseidon(circuit) = vk.circuit() else {
+        panic!("Fixture vk should use Poseidon config");
+    };
+
+    let cost = estimate(&vk, false);
+    let compressed = estimate(&vk, true);
+
+    assert_eq!(cost, estimate(&vk, false));
+    assert!(cost.permutations > 0 && cost.field_ops > 0);
+    assert!(compressed.permutations > cost.permutations);
+    assert_eq!(
+        cost,
+        estimate_circuit(&circuit.common, Plonky2Config::Poseidon, false)
+    );
+    assert_ne!(
+        cost,
+        estimate_circuit(&circuit.common, Plonky2Config::Keccak, false)
+    );
+}
+
+#[rstest]
+fn should_count_operations_deterministically(valid_test_data: TestData) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let (result, counters) = verify_instrumented(&vk, &proof, &pubs);
+    let (result_compressed, counters_compressed) =
+        verify_instrumented(&vk, &proof_compressed, &pubs);
+
+    assert!(result.is_ok());
+    assert!(result_compressed.is_ok());
+    assert_eq!(counters, verify_instrumented(&vk, &proof, &pubs).1);
+
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<2>>::F;
+    let common = deserialize_vk::<F, C, 2>(&vk.bytes).unwrap().common;
+    let constraints: u64 = common
+        .gates
+        .iter()
+        .map(|gate| gate.0.num_constraints() as u64)
+        .sum();
+    // Constraints reduced with 2 challenges, with 80 routed wires and 9 partial products,
+    // then the final polynomial of 8 coefficients evaluated in each of the 28 query rounds.
+    let extension_muls =
+        constraints + 2 * (common.num_gate_constraints as u64 + 2 * 80 + 9) + 28 * 8;
+    // Transcript of 96 permutations and a public inputs hash, then 28 query rounds opening
+    // leaves of 84, 135, 20 and 16 elements with paths of 2 siblings and no folding.
+    let verification = OpCounters {
+        hash_permutations: 96 + 1 + 28 * (11 + 17 + 3 + 2),
+        hash_compressions: 28 * 4 * 2,
+        merkle_paths: 28 * 4,
+        field_muls: 28 * (84 + 135 + 20 + 16) * 2,
+        extension_muls,
+        fri_folds: 0,
+    };
+    assert_eq!(counters, verification);
+    let prepared = PreparedVk::new(&vk).unwrap();
+    assert_eq!(estimate(&prepared, false), Cost::from(counters));
+    assert_eq!(estimate(&prepared, true), Cost::from(counters_compressed));
+    // Compressed proofs replay the transcript, then `plonky2` derives the challenges again
+    // and rebuilds the leaves, paths and combined openings of every query before verifying.
+    assert_eq!(
+        counters_compressed,
+        OpCounters {
+            hash_permutations: 2 * (96 + 1) + 2 * 28 * (11 + 17 + 3 + 2),
+            hash_compressions: 2 * verification.hash_compressions,
+            merkle_paths: 2 * verification.merkle_paths,
+            field_muls: 2 * verification.field_muls,
+            ..verification
+        }
+    );
+
+    // Proofs failing the proof of work stop before the query rounds.
+    let mut tampered = proof.clone();
+    let len = tampered.bytes.len();
+    tampered.bytes[len - 1] = pubs.last().unwrap().wrapping_add(1);
+    let (result, counters) = verify_instrumented(&vk, &tampered, &pubs);
+    assert!(matches!(
+        result,
+        Err(VerifyError::Failure {
+            reason: FailureReason::ProofOfWork
+        })
+    ));
+    assert_eq!(
+        counters,
+        OpCounters {
+            hash_permutations: 96 + 1,
+            extension_muls: extension_muls - 28 * 8,
+            ..OpCounters::default()
+        }
+    );
+
+    // Proofs with a corrupted leaf stop at its Merkle path, the first of the first round.
+    let mut tampered = proof.clone();
+    tampered.bytes[QUERY_ROUNDS_OFFSET] = tampered.bytes[QUERY_ROUNDS_OFFSET].wrapping_add(1);
+    let (result, counters) = verify_instrumented(&vk, &tampered, &pubs);
+    assert!(matches!(
+        result,
+        Err(VerifyError::Failure {
+            reason: FailureReason::MerklePath
+        })
+    ));
+    assert_eq!(
+        counters,
+        OpCounters {
+            hash_permutations: 96 + 1 + 11,
+            hash_compressions: 2,
+            merkle_paths: 1,
+            extension_muls: extension_muls - 28 * 8,
+            ..OpCounters::default()
+        }
+    );
+
+    // Proofs rejected by the replay only count the replay.
+    let mut tampered = pubs.clone();
+    tampered[16] ^= 1;
+    let (result, counters) = verify_instrumented(&vk, &proof_compressed, &tampered);
+    assert!(result.is_err());
+    assert_eq!(
+        counters,
+        OpCounters {
+            hash_permutations: 96 + 1,
+            ..OpCounters::default()
+        }
+    );
+
+    let keccak = Vk {
+        config: Plonky2Config::Keccak,
+        bytes: vk.bytes,
+    };
+    let (result, counters) = verify_instrumented(&keccak, &proof, &pubs);
+    assert!(result.is_err());
+    assert_eq!(counters, OpCounters::default());
+}
+
+#[rstest]
+fn should_stop_verification_when_fuel_runs_out(
+    valid_test_data: TestData,
+    #[values(false, true)] compressed: bool,
+) {
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+    let proof = if compressed { proof_compressed } else { proof };
+
+    let mut fuel = u64::MAX;
+    assert!(verify_with_fuel(&vk, &proof, &pubs, &mut fuel).is_ok());
+    let consumed = u64::MAX - fuel;
+    let (_, counters) = verify_instrumented(&vk, &proof, &pubs);
+    // Preparing the vk is charged 3 units per 8 bytes and a permutation per 8 of them plus
+    // the domain separator, decoding a unit per 8 bytes, then the instrumented operations.
+    let words = vk.bytes.len().div_ceil(8) as u64;
+    let preparation = 3 * words + (words.div_ceil(8) + 1) * 1000;
+    let decoding = (proof.bytes.len() + pubs.len()).div_ceil(8) as u64;
+    assert_eq!(consumed, preparation + decoding + counters.fuel());
+
+    let mut fuel = consumed;
+    assert!(verify_with_fuel(&vk, &proof, &pubs, &mut fuel).is_ok());
+    assert_eq!(fuel, 0);
+
+    for budget in [0, consumed / 2, consumed - 1] {
+        let mut fuel = budget;
+        assert!(matches!(
+            verify_with_fuel(&vk, &proof, &pubs, &mut fuel),
+            Err(VerifyError::BudgetExhausted { required, remaining })
+                if remaining == fuel && required > remaining
+        ));
+    }
+
+    // Preparing the vk is paid before decoding it.
+    let mut fuel = preparation - 1;
+    assert!(matches!(
+        verify_with_fuel(&vk, &proof, &pubs, &mut fuel),
+        Err(VerifyError::BudgetExhausted { required, .. }) if required == preparation
+    ));
+}
+
+#[cfg(feature = "scale")]
+#[rstest]
+fn should_round_trip_scale_encoding(valid_test_data: TestData) {
+    use codec::{Decode, Encode};
+
+    let TestData {
+        vk,
+        proof,
+        proof_compressed,
+        pubs,
+    } = valid_test_data;
+
+    let encoded = vk.encode();
+    assert_eq!(encoded[0], Plonky2Config::Poseidon.encode()[0]);
+    let decoded = Vk::decode(&mut encoded.as_slice()).unwrap();
+    assert_eq!(decoded.config, vk.config);
+    assert_eq!(decoded.bytes, vk.bytes);
+
+    for proof in [&proof, &proof_compressed] {
+        let decoded = Proof::decode(&mut proof.encode().as_slice()).unwrap();
+        assert_eq!(decoded.compressed, proof.compressed);
+        assert_eq!(decoded.bytes, proof.bytes);
+        assert!(verify(&vk, &decoded, &pubs).is_ok());
+    }
+
+    let typed = Pubs { bytes: pubs };
+    assert_eq!(Pubs::decode(&mut typed.encode().as_slice()).unwrap(), typed);
+
+    for config in [Plonky2Config::Keccak, Plonky2Config::Poseidon] {
+        assert_eq!(
+            Plonky2Config::decode(&mut config.encode().as_slice()).unwrap(),
+            config
+        );
+    }
+}
+
+#[cfg(feature = "scale")]
+#[rstest]
+fn should_bound_scale_encoding(valid_test_data: TestData) {
+    use codec::{Decode, Encode, MaxEncodedLen};
+    use plonky2_verifier::scale::{BoundedProof, BoundedVk};
+
+    const VK_SIZE: u32 = 1541;
+    const PROOF_SIZE: u32 = 70184;
+
+    let TestData { vk, proof, .. } = valid_test_data;
+    assert_eq!(vk.bytes.len(), VK_SIZE as usize);
+    assert_eq!(proof.bytes.len(), PROOF_SIZE as usize);
+    let encoded_vk = vk.encode();
+    let encoded_proof = proof.encode();
+
+    let bounded = BoundedVk::<VK_SIZE>::decode(&mut encoded_vk.as_slice()).unwrap();
+    assert_eq!(bounded.encode(), encoded_vk);
+    assert!(encoded_vk.len() <= BoundedVk::<VK_SIZE>::max_encoded_len());
+    assert!(BoundedVk::<{ VK_SIZE - 1 }>::decode(&mut encoded_vk.as_slice()).is_err());
+    assert!(BoundedVk::<{ VK_SIZE - 1 }>::try_from(bounded.into_inner()).is_err());
+
+    let bounded = BoundedProof::<PROOF_SIZE>::decode(&mut encoded_proof.as_slice()).unwrap();
+    assert_eq!(bounded.encode(), encoded_proof);
+    assert!(encoded_proof.len() <= BoundedProof::<PROOF_SIZE>::max_encoded_len());
+    assert!(BoundedProof::<{ PROOF_SIZE - 1 }>::decode(&mut encoded_proof.as_slice()).is_err());
+    assert_eq!(Plonky2Config::max_encoded_len(), 1);
+}
+
+#[cfg(feature = "scale")]
+#[rstest]
+fn should_round_trip_scale_encoded_errors(valid_test_data: TestData) {
+    use codec::{Decode, Encode};
+
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+    let keccak = Vk {
+        config: Plonky2Config::Keccak,
+        bytes: vk.bytes.clone(),
+    };
+    let mut corrupted = proof.bytes.clone();
+    corrupted.truncate(corrupted.len() / 2);
+    let corrupted = Proof {
+        compressed: false,
+        bytes: corrupted,
+    };
+
+    let errors = [
+        verify(&keccak, &proof, &pubs).unwrap_err(),
+        verify(&vk, &corrupted, &pubs).unwrap_err(),
+        verify(&vk, &proof, &pubs[..8]).unwrap_err(),
+    ];
+    for err in errors {
+        let decoded = VerifyError::decode(&mut err.encode().as_slice()).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", err));
+    }
+
+    let err = validate_vk(&keccak).unwrap_err();
+    let decoded = ValidateError::decode(&mut err.encode().as_slice()).unwrap();
+    assert_eq!(format!("{:?}", decoded), format!("{:?}", err));
+}
+
+#[cfg(feature = "scale")]
+#[rstest]
+fn should_bound_scale_encoded_errors(valid_test_data: TestData) {
+    use codec::{Decode, Encode, MaxEncodedLen};
+    use plonky2_verifier::scale::{BoundedValidateError, BoundedVerifyError};
+
+    let TestData {
+        vk, proof, pubs, ..
+    } = valid_test_data;
+    let policy = VerifierPolicy::default().with_allowed_gates(["NoopGate"]);
+
+    let err = verify_with_policy(&vk, &proof, &pubs, &policy).unwrap_err();
+    let VerifyError::ForbiddenGates { gates } = &err else {
+        panic!("unexpected error: {:?}", err);
+    };
+    let count = gates.len() as u64;
+    let bounded = BoundedVerifyError::from(err);
+    assert_eq!(bounded, BoundedVerifyError::ForbiddenGates { count });
+    let encoded = bounded.encode();
+    assert!(encoded.len() <= BoundedVerifyError::max_encoded_len());
+    assert_eq!(
+        BoundedVerifyError::decode(&mut encoded.as_slice()).unwrap(),
+        bounded
+    );
+
+    let err = validate_vk_with_policy(&vk, &policy).unwrap_err();
+    let ValidateError::ForbiddenGates { gates } = err.clone() else {
+        panic!("unexpected error: {:?}", err);
+    };
+    let count = gates.len() as u64;
+    let bounded = BoundedValidateError::from(err);
+    assert_eq!(bounded, BoundedValidateError::ForbiddenGates { count });
+    let encoded = bounded.encode();
+    assert!(encoded.len() <= BoundedValidateError::max_encoded_len());
+    assert_eq!(
+        BoundedValidateError::decode(&mut encoded.as_slice()).unwrap(),
+        bounded
     );
 }